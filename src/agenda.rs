@@ -0,0 +1,632 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::{
+    fs,
+    time::{Instant, SystemTime},
+};
+use tokio::time;
+
+use crate::{
+    display::LinearMatrixDisplay,
+    error::Error,
+    model::{self, Language},
+    text,
+};
+
+/// Recurring events are looked through for this number of periods at most
+const MAX_PERIODS: i64 = 100_000;
+/// The calendar file is checked for changes this often
+const REFRESH_SEC: u64 = 30;
+/// The reminder is shown only when it is noticed in time, not after a jump of the clock
+const REMIND_WITHIN_SEC: i64 = 60;
+
+/// Time zone of the calendar time
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Zone {
+    Utc,
+    Named(Tz),
+    /// floating time, or a time zone unknown to the clock, the local zone is used
+    Local,
+}
+
+impl Zone {
+    /// A time skipped by the DST switch is moved an hour later
+    fn utc(self, time: NaiveDateTime) -> Option<DateTime<Utc>> {
+        let convert = |time| match self {
+            Zone::Utc => Some(DateTime::from_utc(time, Utc)),
+            Zone::Named(tz) => tz.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc)),
+            Zone::Local => Local
+                .from_local_datetime(&time)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+        };
+        convert(time).or_else(|| convert(time + Duration::hours(1)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Supported part of RRULE: FREQ from DAILY to YEARLY, INTERVAL, COUNT, UNTIL, BYDAY for daily, weekly and monthly rules
+#[derive(Debug, Clone)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<DateTime<Utc>>,
+    /// days of the week with their number in the month for monthly rules, `-1` is the last one
+    by_day: Vec<(Option<i32>, Weekday)>,
+}
+
+impl Rule {
+    /// Number of the period the date is in, periods start from the one of `start`
+    fn period(&self, start: NaiveDate, date: NaiveDate) -> i64 {
+        let periods = match self.frequency {
+            Frequency::Daily => (date - start).num_days(),
+            Frequency::Weekly => (monday(date) - monday(start)).num_weeks(),
+            Frequency::Monthly => {
+                (date.year() - start.year()) as i64 * 12 + date.month0() as i64 - start.month0() as i64
+            }
+            Frequency::Yearly => (date.year() - start.year()) as i64,
+        };
+        periods.div_euclid(self.interval as i64)
+    }
+
+    /// Dates of the occurrences in the period (day, week, month or year) with the number, sorted
+    fn dates(&self, start: NaiveDate, period: i64) -> Vec<NaiveDate> {
+        let step = period * self.interval as i64;
+        let mut dates: Vec<NaiveDate> = match self.frequency {
+            Frequency::Daily => {
+                let date = start + Duration::days(step);
+                let day = date.weekday();
+                (self.by_day.is_empty() || self.by_day.iter().any(|(_, d)| *d == day))
+                    .then_some(date)
+                    .into_iter()
+                    .collect()
+            }
+            Frequency::Weekly if self.by_day.is_empty() => vec![start + Duration::weeks(step)],
+            Frequency::Weekly => {
+                let monday = monday(start) + Duration::weeks(step);
+                self.by_day
+                    .iter()
+                    .map(|(_, day)| monday + Duration::days(day.num_days_from_monday() as i64))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let months = start.year() as i64 * 12 + start.month0() as i64 + step;
+                let (year, month) = ((months / 12) as i32, (months % 12) as u32 + 1);
+                if self.by_day.is_empty() {
+                    NaiveDate::from_ymd_opt(year, month, start.day()).into_iter().collect()
+                } else {
+                    self.by_day
+                        .iter()
+                        .flat_map(|(n, day)| match n {
+                            Some(n) => vec![nth_weekday(year, month, *n, *day)],
+                            None => (1..=5).map(|n| nth_weekday(year, month, n, *day)).collect(),
+                        })
+                        .flatten()
+                        .collect()
+                }
+            }
+            Frequency::Yearly => NaiveDate::from_ymd_opt(start.year() + step as i32, start.month(), start.day())
+                .into_iter()
+                .collect(),
+        };
+        dates.sort();
+        dates
+    }
+}
+
+fn monday(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// N-th week day of the month, counted from the end when negative
+fn nth_weekday(year: i32, month: u32, n: i32, day: Weekday) -> Option<NaiveDate> {
+    let date = if n > 0 {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let offset = (7 + day.num_days_from_monday() - first.weekday().num_days_from_monday()) % 7;
+        first + Duration::days(offset as i64 + 7 * (n as i64 - 1))
+    } else {
+        let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        let last = NaiveDate::from_ymd_opt(next_year, next_month, 1)? - Duration::days(1);
+        let offset = (7 + last.weekday().num_days_from_monday() - day.num_days_from_monday()) % 7;
+        last - Duration::days(offset as i64 + 7 * (-n as i64 - 1))
+    };
+    (date.month() == month).then_some(date)
+}
+
+#[derive(Debug, Clone)]
+struct Event {
+    uid: Option<String>,
+    summary: String,
+    start: NaiveDateTime,
+    zone: Zone,
+    all_day: bool,
+    rule: Option<Rule>,
+    /// occurrences removed from the rule or moved to other events
+    exceptions: Vec<DateTime<Utc>>,
+    /// set for a changed occurrence of a recurring event
+    recurrence_id: Option<DateTime<Utc>>,
+}
+
+impl Event {
+    /// Start of the first occurrence after the time and not after the limit
+    fn next_after(&self, time: &DateTime<Utc>, limit: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let rule = match &self.rule {
+            Some(rule) => rule,
+            None => return self.zone.utc(self.start).filter(|start| start > time && start <= limit),
+        };
+        // occurrences are counted from the first one, otherwise the search starts a period before the time
+        // as the time zone of the event may put it on another date
+        let first = match rule.count {
+            Some(_) => 0,
+            None => (rule.period(self.start.date(), time.naive_utc().date()) - 1).max(0),
+        };
+        let mut count = 0;
+        for period in first..first + MAX_PERIODS {
+            for date in rule.dates(self.start.date(), period) {
+                let start = date.and_time(self.start.time());
+                let utc = match self.zone.utc(start) {
+                    Some(utc) if start >= self.start => utc,
+                    _ => continue,
+                };
+                // excluded occurrences are counted too
+                count += 1;
+                if rule.count.is_some_and(|c| count > c) || rule.until.is_some_and(|until| utc > until) || utc > *limit
+                {
+                    return None;
+                }
+                if utc > *time && !self.exceptions.contains(&utc) {
+                    return Some(utc);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Content line split into the name, parameters and the value
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl<'a> Property<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        // parameter values may be quoted and contain colons
+        let mut quoted = false;
+        let colon = line.find(|c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ':' && !quoted
+        })?;
+        let mut head = line[..colon].split(';');
+        let name = head.next()?.to_ascii_uppercase();
+        let params = head
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"')))
+            .collect();
+        Some(Property { name, params, value: &line[colon + 1..] })
+    }
+
+    fn param(&self, name: &str) -> Option<&'a str> {
+        self.params.iter().find(|(key, _)| key == name).map(|(_, value)| *value)
+    }
+
+    /// Date or date-time value, `true` for a date
+    fn time(&self) -> Option<(NaiveDateTime, Zone, bool)> {
+        time(self.value, self.param("TZID"), self.param("VALUE") == Some("DATE"))
+    }
+}
+
+fn time(value: &str, tzid: Option<&str>, date: bool) -> Option<(NaiveDateTime, Zone, bool)> {
+    let value = value.trim();
+    if date || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((date.and_hms(0, 0, 0), Zone::Local, true));
+    }
+    let zone = match (value.strip_suffix('Z'), tzid) {
+        (Some(_), _) => Zone::Utc,
+        // some calendars prefix the names with a slash
+        (None, Some(tzid)) => tzid.trim_start_matches('/').parse().map_or(Zone::Local, Zone::Named),
+        (None, None) => Zone::Local,
+    };
+    let time = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
+    Some((time, zone, false))
+}
+
+fn weekday(value: &str) -> Option<Weekday> {
+    Some(match value {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// `None` when the rule uses what is not supported
+fn rule(value: &str, zone: Zone) -> Option<Rule> {
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: vec![],
+    };
+    for part in value.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                rule.frequency = match value {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                }
+            }
+            "INTERVAL" => rule.interval = value.parse().ok().filter(|i| *i > 0)?,
+            "COUNT" => rule.count = Some(value.parse().ok()?),
+            "UNTIL" => {
+                // a date is included as a whole
+                let (until, until_zone, date) = time(value, None, false)?;
+                let until = if date {
+                    until + Duration::days(1) - Duration::seconds(1)
+                } else {
+                    until
+                };
+                rule.until = (if until_zone == Zone::Utc { until_zone } else { zone }).utc(until);
+            }
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let (n, day) = day.split_at(day.len().checked_sub(2)?);
+                    let n = if n.is_empty() { None } else { Some(n.parse().ok()?) };
+                    rule.by_day.push((n, weekday(day)?));
+                }
+            }
+            // week start only matters for the rules with the other parts which are not supported
+            "WKST" => {}
+            _ => return None,
+        }
+    }
+    // the numbers of the days are for monthly rules, yearly rules with the days need BYMONTH and BYWEEKNO
+    let numbered = rule.by_day.iter().any(|(n, _)| n.is_some());
+    match rule.frequency {
+        Frequency::Daily | Frequency::Weekly if numbered => None,
+        Frequency::Yearly if !rule.by_day.is_empty() => None,
+        _ => Some(rule),
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+/// VEVENT components of the calendar, events which can not be understood are skipped
+fn parse(ics: &str) -> Vec<Event> {
+    // long lines are folded, continuation lines start with a space or a tab
+    let mut lines: Vec<String> = vec![];
+    for line in ics.lines() {
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = vec![];
+    let mut components = vec![];
+    let mut properties = vec![];
+    for line in &lines {
+        let property = match Property::parse(line) {
+            Some(property) => property,
+            None => continue,
+        };
+        match property.name.as_str() {
+            "BEGIN" => components.push(property.value.to_ascii_uppercase()),
+            "END" if components.pop().as_deref() == Some("VEVENT") => {
+                events.extend(event(&properties));
+                properties.clear();
+            }
+            "END" => {}
+            // properties of alarms inside the event are not its own
+            _ if components.last().map(String::as_str) == Some("VEVENT") => properties.push(property),
+            _ => {}
+        }
+    }
+
+    // changed occurrences replace the ones of the recurring event
+    let moved = events
+        .iter()
+        .filter_map(|e| Some((e.uid.clone()?, e.recurrence_id?)))
+        .collect::<Vec<_>>();
+    for event in events.iter_mut().filter(|e| e.rule.is_some()) {
+        for (uid, id) in &moved {
+            if event.uid.as_ref() == Some(uid) {
+                event.exceptions.push(*id);
+            }
+        }
+    }
+    events
+}
+
+fn event(properties: &[Property]) -> Option<Event> {
+    let get = |name: &str| properties.iter().find(|p| p.name == name);
+    let (start, zone, all_day) = get("DTSTART")?.time()?;
+    let rule = match get("RRULE") {
+        Some(property) => Some(rule(property.value, zone)?),
+        None => None,
+    };
+    let occurrence = |property: &Property, value: &str| {
+        let (time, time_zone, _) = time(value, property.param("TZID"), property.param("VALUE") == Some("DATE"))?;
+        time_zone.utc(time)
+    };
+    let exceptions = properties
+        .iter()
+        .filter(|p| p.name == "EXDATE")
+        .flat_map(|p| p.value.split(',').filter_map(move |value| occurrence(p, value)))
+        .collect();
+    let recurrence_id = get("RECURRENCE-ID").and_then(|p| occurrence(p, p.value));
+
+    Some(Event {
+        uid: get("UID").map(|p| p.value.to_string()),
+        summary: get("SUMMARY").map_or_else(String::new, |p| unescape(p.value)),
+        start,
+        zone,
+        all_day,
+        rule,
+        exceptions,
+        recurrence_id,
+    })
+}
+
+/// Events of the calendar file, read again when the file changes
+pub(crate) struct Agenda<'a> {
+    config: &'a model::Agenda,
+    events: Vec<Event>,
+    modified: Option<SystemTime>,
+    failed: bool,
+    /// when the file has been checked for changes
+    refreshed: Option<Instant>,
+    checked: Option<DateTime<Utc>>,
+}
+
+impl<'a> Agenda<'a> {
+    pub fn new(config: &'a model::Agenda) -> Self {
+        Agenda {
+            config,
+            events: vec![],
+            modified: None,
+            failed: false,
+            refreshed: None,
+            checked: None,
+        }
+    }
+
+    /// Text about the next event, `None` when there are no events soon
+    pub fn text(&mut self, time: &DateTime<Local>, language: Language) -> Option<String> {
+        let (start, event) = self.next(&time.with_timezone(&Utc))?;
+        Some(describe(event, &start.with_timezone(&Local), time, language))
+    }
+
+    /// Text about the event starting in `flash_min` minutes, when the time has come since the previous call
+    pub fn reminder(&mut self, time: &DateTime<Local>, language: Language) -> Option<String> {
+        if self.config.flash_min == 0 {
+            return None;
+        }
+        let ahead = Duration::minutes(self.config.flash_min as i64);
+        let now = time.with_timezone(&Utc) + ahead;
+        let checked = self.checked.replace(now)?;
+        let (start, event) = self.next(&checked)?;
+        if start > now || now - start >= Duration::seconds(REMIND_WITHIN_SEC) {
+            return None;
+        }
+        Some(describe(event, &start.with_timezone(&Local), time, language))
+    }
+
+    /// The earliest event within the lookahead after the time
+    fn next(&mut self, time: &DateTime<Utc>) -> Option<(DateTime<Utc>, &Event)> {
+        self.refresh();
+        let limit = *time + Duration::days(self.config.lookahead_days as i64);
+        self.events
+            .iter()
+            .filter_map(|event| Some((event.next_after(time, &limit)?, event)))
+            .min_by_key(|(start, _)| *start)
+    }
+
+    /// Reads the calendar when the file has been changed, the events read before are kept on errors
+    fn refresh(&mut self) {
+        let interval = std::time::Duration::from_secs(REFRESH_SEC);
+        if self.refreshed.is_some_and(|refreshed| refreshed.elapsed() < interval) {
+            return;
+        }
+        self.refreshed = Some(Instant::now());
+        let read = fs::metadata(&self.config.file)
+            .and_then(|metadata| metadata.modified())
+            .and_then(|modified| match self.modified {
+                Some(loaded) if loaded == modified => Ok(None),
+                _ => fs::read_to_string(&self.config.file).map(|ics| Some((modified, ics))),
+            });
+        match read {
+            Ok(Some((modified, ics))) => {
+                self.events = parse(&ics);
+                self.modified = Some(modified);
+                self.failed = false;
+                println!("Calendar `{}` is read, {} events", self.config.file, self.events.len());
+            }
+            Ok(None) => {}
+            Err(e) if !self.failed => {
+                self.failed = true;
+                eprintln!("Calendar `{}` can not be read: {}", self.config.file, e);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// "next: 14:30 Standup", the day of the week is added for the events not today
+fn describe(event: &Event, start: &DateTime<Local>, now: &DateTime<Local>, language: Language) -> String {
+    let (next, days) = match language {
+        Language::En => ("next", ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]),
+        Language::Ru => ("далее", ["Пн", "Вт", "Ср", "Чт", "Пт", "Сб", "Вс"]),
+    };
+    let mut text = format!("{}:", next);
+    if start.date() != now.date() {
+        text = format!("{} {}", text, days[start.weekday().num_days_from_monday() as usize]);
+    }
+    if !event.all_day {
+        text = format!("{} {}", text, start.format("%H:%M"));
+    }
+    format!("{} {}", text, event.summary)
+}
+
+/// Flashes the whole display and shows the text
+pub(crate) async fn flash(display: &mut LinearMatrixDisplay, text: &str, scroll_msec: u64) -> Result<(), Error> {
+    for _ in 0..3 {
+        display.draw(|_, _| 1)?;
+        time::sleep(std::time::Duration::from_millis(150)).await;
+        display.clear()?;
+        time::sleep(std::time::Duration::from_millis(150)).await;
+    }
+    text::scroll(display, text, scroll_msec).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    fn calendar(events: &[&str]) -> String {
+        let events = events
+            .iter()
+            .map(|event| format!("BEGIN:VEVENT\r\n{}\r\nEND:VEVENT\r\n", event))
+            .collect::<String>();
+        format!("BEGIN:VCALENDAR\r\n{}END:VCALENDAR\r\n", events)
+    }
+
+    fn recurring(start: &str, rule: &str) -> Event {
+        let ics = calendar(&[&format!("SUMMARY:Test\r\nDTSTART:{}\r\nRRULE:{}", start, rule)]);
+        parse(&ics).pop().expect("the rule is supported")
+    }
+
+    fn next(event: &Event, time: &str) -> Option<DateTime<Utc>> {
+        event.next_after(&utc(time), &(utc(time) + Duration::days(60)))
+    }
+
+    #[test]
+    fn rejects_unsupported_rules() {
+        for value in [
+            "FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+            "FREQ=MONTHLY;BYMONTHDAY=15",
+            "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+            "FREQ=DAILY;BYHOUR=9,17",
+            "FREQ=YEARLY;BYDAY=MO",
+            "FREQ=DAILY;BYDAY=1MO",
+            "FREQ=HOURLY",
+        ] {
+            assert!(rule(value, Zone::Utc).is_none(), "{}", value);
+        }
+        assert!(rule("FREQ=WEEKLY;WKST=SU;BYDAY=MO", Zone::Utc).is_some());
+    }
+
+    #[test]
+    fn daily_rule_filters_days() {
+        let workdays = recurring("20220103T090000Z", "FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR;COUNT=6");
+        assert_eq!(
+            next(&workdays, "2022-01-04T10:00:00Z"),
+            Some(utc("2022-01-05T09:00:00Z"))
+        );
+        assert_eq!(
+            next(&workdays, "2022-01-07T10:00:00Z"),
+            Some(utc("2022-01-10T09:00:00Z"))
+        );
+        assert_eq!(next(&workdays, "2022-01-10T10:00:00Z"), None);
+    }
+
+    #[test]
+    fn starts_from_the_current_period() {
+        let daily = recurring("19700101T120000Z", "FREQ=DAILY");
+        assert_eq!(next(&daily, "2030-06-15T13:00:00Z"), Some(utc("2030-06-16T12:00:00Z")));
+
+        // every other week, 2029-12-31 is in an odd week since the start
+        let fortnightly = recurring("20220103T090000Z", "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU,TH");
+        assert_eq!(
+            next(&fortnightly, "2030-01-01T00:00:00Z"),
+            Some(utc("2030-01-08T09:00:00Z"))
+        );
+
+        let last_friday = recurring("20000101T180000Z", "FREQ=MONTHLY;BYDAY=-1FR");
+        assert_eq!(
+            next(&last_friday, "2022-01-01T00:00:00Z"),
+            Some(utc("2022-01-28T18:00:00Z"))
+        );
+
+        let yearly = recurring("19900305T080000Z", "FREQ=YEARLY;INTERVAL=4");
+        assert_eq!(next(&yearly, "2022-01-10T00:00:00Z"), Some(utc("2022-03-05T08:00:00Z")));
+        // the next one is beyond the lookahead
+        assert_eq!(next(&yearly, "2022-04-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn skips_exceptions_and_moved_occurrences() {
+        let ics = calendar(&[
+            "UID:standup\r\nSUMMARY:Standup\r\nDTSTART:20220103T090000Z\r\nRRULE:FREQ=WEEKLY\r\n\
+             EXDATE:20220110T090000Z",
+            "UID:standup\r\nSUMMARY:Standup\r\nDTSTART:20220118T100000Z\r\nRECURRENCE-ID:20220117T090000Z",
+        ]);
+        let events = parse(&ics);
+        assert_eq!(
+            next(&events[0], "2022-01-04T00:00:00Z"),
+            Some(utc("2022-01-24T09:00:00Z"))
+        );
+        assert_eq!(
+            next(&events[1], "2022-01-04T00:00:00Z"),
+            Some(utc("2022-01-18T10:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn reads_the_file_when_it_changes() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "{}", calendar(&["SUMMARY:Dentist\r\nDTSTART:20220110T150000Z"])).unwrap();
+        let config = model::Agenda {
+            file: file.path().display().to_string(),
+            lookahead_days: 7,
+            flash_min: 0,
+        };
+        let mut agenda = Agenda::new(&config);
+        agenda.refresh();
+        assert_eq!(agenda.events.len(), 1);
+
+        // the file is not looked at again till the refresh interval passes
+        fs::write(file.path(), calendar(&[])).unwrap();
+        agenda.refresh();
+        assert_eq!(agenda.events.len(), 1);
+        agenda.refreshed = None;
+        agenda.refresh();
+        assert_eq!(agenda.events.len(), 0);
+    }
+}
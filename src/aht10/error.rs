@@ -13,6 +13,15 @@ where
     #[snafu(display("AHT10 measure error"))]
     MeasureError { source: I2CError },
 
+    #[snafu(display("AHT10 reset error"))]
+    ResetError { source: I2CError },
+
     #[snafu(display("AHT10 is not calibrated yet"))]
     UncalibratedError {},
+
+    #[snafu(display("AHT10 is busy for too long"))]
+    TimeoutError {},
+
+    #[snafu(display("AHT2x data CRC mismatch"))]
+    CrcError {},
 }
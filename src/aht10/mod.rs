@@ -4,10 +4,21 @@ use embedded_hal::blocking::i2c::{Read, Write};
 use error::Error;
 use snafu::ResultExt;
 
+use crate::crc::crc8;
+
 const I2C_ADDRESS: u8 = 0x38; // AHT10 has it's own static address
 
 const CMD_INIT: [u8; 3] = [0b11100001, 0b00001000, 0];
+const CMD_INIT_AHT2X: [u8; 3] = [0b10111110, 0b00001000, 0];
 const CMD_MEASURE: [u8; 3] = [0b10101100, 0b00110011, 0];
+const CMD_SOFT_RESET: [u8; 1] = [0b10111010];
+
+const POWER_ON_MS: u32 = 40;
+const SOFT_RESET_MS: u32 = 20;
+const INIT_MS: u32 = 10;
+pub const MEASURE_MS: u32 = 80;
+const POLL_MS: u32 = 10;
+const BUSY_TIMEOUT_MS: u32 = 300;
 
 bitflags! {
     struct StatusFlags: u8 {
@@ -17,9 +28,20 @@ bitflags! {
     }
 }
 
+/// Members of the family differ in the init command and the CRC after the data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Variant {
+    Aht10,
+    /// AHT20 and AHT21
+    Aht2x,
+}
+
 pub struct AHT10<I2C> {
     pub(crate) i2c: I2C,
     pub(crate) delay_ms: fn(u32) -> (),
+    pub(crate) variant: Variant,
+    /// the sensor has already been reset during the started measurement
+    pub(crate) reset_once: bool,
 }
 
 impl<I2C, I2CError> AHT10<I2C>
@@ -27,19 +49,90 @@ where
     I2CError: std::error::Error,
     I2C: Read<Error = I2CError> + Write<Error = I2CError>,
 {
+    pub fn new(i2c: I2C, variant: Variant, delay_ms: fn(u32) -> ()) -> Self {
+        AHT10 { i2c, delay_ms, variant, reset_once: false }
+    }
+
+    /// Loads the calibration, AHT2x needs it only when the status says it is not calibrated
     pub fn init(&mut self) -> Result<(), Error<I2CError>> {
-        self.i2c.write(I2C_ADDRESS, &CMD_INIT).context(error::InitSnafu)?;
-        (self.delay_ms)(300);
-        Ok(())
+        match self.variant {
+            Variant::Aht10 => self.i2c.write(I2C_ADDRESS, &CMD_INIT).context(error::InitSnafu)?,
+            Variant::Aht2x => {
+                (self.delay_ms)(POWER_ON_MS);
+                if self.status()?.contains(StatusFlags::CALIBRATION) {
+                    return Ok(());
+                }
+                self.i2c.write(I2C_ADDRESS, &CMD_INIT_AHT2X).context(error::InitSnafu)?;
+            }
+        }
+        (self.delay_ms)(INIT_MS);
+        self.wait().map(|_| ())
     }
 
-    /// Measures Temperature and Relative Humidity
+    /// Restarts the sensor as it is after power-on and loads the calibration
+    pub fn reset(&mut self) -> Result<(), Error<I2CError>> {
+        self.i2c
+            .write(I2C_ADDRESS, &CMD_SOFT_RESET)
+            .context(error::ResetSnafu)?;
+        (self.delay_ms)(SOFT_RESET_MS);
+        self.init()
+    }
+
+    /// Measures Temperature and Relative Humidity, the sensor is reset once when it has lost its calibration
     pub fn measure(&mut self) -> Result<(f32, f32), Error<I2CError>> {
-        self.i2c.write(I2C_ADDRESS, &CMD_MEASURE).context(error::MeasureSnafu)?;
-        (self.delay_ms)(100);
+        match self.measure_once() {
+            Err(Error::UncalibratedError {}) => {
+                self.reset()?;
+                self.measure_once()
+            }
+            result => result,
+        }
+    }
 
-        let buf: &mut [u8; 6] = &mut [0; 6];
-        self.i2c.read(I2C_ADDRESS, buf).context(error::MeasureSnafu)?;
+    fn measure_once(&mut self) -> Result<(f32, f32), Error<I2CError>> {
+        self.start_measurement()?;
+        (self.delay_ms)(MEASURE_MS);
+        self.wait()?;
+        self.read_measurement()
+    }
+
+    /// Triggers a measurement without waiting for it, it takes about `MEASURE_MS`
+    pub fn start_measurement(&mut self) -> Result<(), Error<I2CError>> {
+        self.reset_once = false;
+        self.i2c.write(I2C_ADDRESS, &CMD_MEASURE).context(error::MeasureSnafu)
+    }
+
+    /// Result of the started measurement, `None` while the sensor is busy.
+    /// The sensor which has lost its calibration is reset and measures again, once per measurement.
+    pub fn poll_result(&mut self) -> Result<Option<(f32, f32)>, Error<I2CError>> {
+        if self.status()?.contains(StatusFlags::BUSY) {
+            return Ok(None);
+        }
+        match self.read_measurement() {
+            Err(Error::UncalibratedError {}) if !self.reset_once => {
+                // rare, a reset blocks for a few dozen milliseconds
+                self.reset()?;
+                self.i2c.write(I2C_ADDRESS, &CMD_MEASURE).context(error::MeasureSnafu)?;
+                self.reset_once = true;
+                Ok(None)
+            }
+            result => result.map(Some),
+        }
+    }
+
+    fn read_measurement(&mut self) -> Result<(f32, f32), Error<I2CError>> {
+        // AHT2x adds the CRC of the status and the data
+        let buf = &mut [0; 7];
+        let len = match self.variant {
+            Variant::Aht10 => 6,
+            Variant::Aht2x => 7,
+        };
+        self.i2c
+            .read(I2C_ADDRESS, &mut buf[..len])
+            .context(error::MeasureSnafu)?;
+        if self.variant == Variant::Aht2x && crc8(&buf[..6]) != buf[6] {
+            return Err(Error::CrcError {});
+        }
         let status = StatusFlags { bits: buf[0] };
         if !status.contains(StatusFlags::CALIBRATION) {
             return Err(Error::UncalibratedError {});
@@ -55,4 +148,176 @@ where
 
         Ok((temp, hum))
     }
+
+    /// Polls the status until the sensor is not busy
+    fn wait(&mut self) -> Result<StatusFlags, Error<I2CError>> {
+        for _ in 0..BUSY_TIMEOUT_MS / POLL_MS {
+            let status = self.status()?;
+            if !status.contains(StatusFlags::BUSY) {
+                return Ok(status);
+            }
+            (self.delay_ms)(POLL_MS);
+        }
+        Err(Error::TimeoutError {})
+    }
+
+    fn status(&mut self) -> Result<StatusFlags, Error<I2CError>> {
+        let buf = &mut [0; 1];
+        self.i2c.read(I2C_ADDRESS, buf).context(error::MeasureSnafu)?;
+        Ok(StatusFlags { bits: buf[0] })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    /// Sensor behind the I2C bus measuring 25°C and 50%
+    struct Device {
+        commands: Vec<Vec<u8>>,
+        calibrated: bool,
+        /// the init command loads the calibration
+        calibrates: bool,
+        /// reads left until the measurement is done
+        busy_reads: usize,
+        busy_after_measure: usize,
+        crc_offset: u8,
+    }
+
+    impl Device {
+        fn new(calibrated: bool, calibrates: bool) -> Self {
+            Device {
+                commands: vec![],
+                calibrated,
+                calibrates,
+                busy_reads: 0,
+                busy_after_measure: 2,
+                crc_offset: 0,
+            }
+        }
+    }
+
+    impl Read for Device {
+        type Error = Infallible;
+
+        fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Infallible> {
+            assert_eq!(address, I2C_ADDRESS);
+            let mut status = if self.calibrated {
+                StatusFlags::CALIBRATION
+            } else {
+                StatusFlags::empty()
+            };
+            if self.busy_reads > 0 {
+                self.busy_reads -= 1;
+                status |= StatusFlags::BUSY;
+            }
+            let mut data = [status.bits, 0x80, 0x00, 0x06, 0x00, 0x00, 0];
+            data[6] = crc8(&data[..6]).wrapping_add(self.crc_offset);
+            buffer.copy_from_slice(&data[..buffer.len()]);
+            Ok(())
+        }
+    }
+
+    impl Write for Device {
+        type Error = Infallible;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Infallible> {
+            assert_eq!(address, I2C_ADDRESS);
+            self.commands.push(bytes.to_vec());
+            if bytes == CMD_MEASURE {
+                self.busy_reads = self.busy_after_measure;
+            } else if bytes == CMD_SOFT_RESET {
+                self.calibrated = false;
+            } else if bytes == CMD_INIT || bytes == CMD_INIT_AHT2X {
+                self.calibrated |= self.calibrates;
+            }
+            Ok(())
+        }
+    }
+
+    fn sensor(device: Device, variant: Variant) -> AHT10<Device> {
+        AHT10::new(device, variant, |_| {})
+    }
+
+    fn commands(sensor: &AHT10<Device>) -> Vec<u8> {
+        sensor.i2c.commands.iter().map(|command| command[0]).collect()
+    }
+
+    #[test]
+    fn waits_while_busy() {
+        let mut device = Device::new(true, true);
+        device.busy_after_measure = 5;
+        let mut sensor = sensor(device, Variant::Aht10);
+        assert_eq!(sensor.measure().unwrap(), (25.0, 50.0));
+        assert_eq!(sensor.i2c.busy_reads, 0);
+
+        // busy for longer than the timeout
+        sensor.i2c.busy_after_measure = 1000;
+        assert!(matches!(sensor.measure(), Err(Error::TimeoutError {})));
+    }
+
+    #[test]
+    fn polls_started_measurement() {
+        let mut sensor = sensor(Device::new(true, true), Variant::Aht2x);
+        sensor.start_measurement().unwrap();
+        assert_eq!(commands(&sensor), [CMD_MEASURE[0]]);
+        assert!(matches!(sensor.poll_result(), Ok(None)));
+        assert!(matches!(sensor.poll_result(), Ok(None)));
+        assert!(matches!(sensor.poll_result(), Ok(Some((t, h))) if t == 25.0 && h == 50.0));
+        // polling only reads
+        assert_eq!(commands(&sensor), [CMD_MEASURE[0]]);
+    }
+
+    #[test]
+    fn resets_uncalibrated_sensor() {
+        let mut sensor = sensor(Device::new(false, true), Variant::Aht10);
+        assert_eq!(sensor.measure().unwrap(), (25.0, 50.0));
+        assert_eq!(
+            commands(&sensor),
+            [CMD_MEASURE[0], CMD_SOFT_RESET[0], CMD_INIT[0], CMD_MEASURE[0]]
+        );
+    }
+
+    #[test]
+    fn resets_once_per_measurement() {
+        let mut sensor = sensor(Device::new(false, false), Variant::Aht10);
+        sensor.i2c.busy_after_measure = 0;
+        sensor.start_measurement().unwrap();
+        assert!(matches!(sensor.poll_result(), Ok(None)));
+        assert!(matches!(sensor.poll_result(), Err(Error::UncalibratedError {})));
+        assert!(matches!(sensor.poll_result(), Err(Error::UncalibratedError {})));
+        assert_eq!(
+            commands(&sensor),
+            [CMD_MEASURE[0], CMD_SOFT_RESET[0], CMD_INIT[0], CMD_MEASURE[0]]
+        );
+
+        // the next measurement may reset it again
+        sensor.i2c.calibrates = true;
+        sensor.start_measurement().unwrap();
+        assert!(matches!(sensor.poll_result(), Ok(None)));
+        assert!(matches!(sensor.poll_result(), Ok(Some((t, h))) if t == 25.0 && h == 50.0));
+    }
+
+    #[test]
+    fn checks_aht2x_crc() {
+        let mut sensor = sensor(Device::new(true, true), Variant::Aht2x);
+        assert_eq!(sensor.measure().unwrap(), (25.0, 50.0));
+        sensor.i2c.crc_offset = 1;
+        assert!(matches!(sensor.measure(), Err(Error::CrcError {})));
+        // AHT10 sends no CRC
+        let mut sensor = self::sensor(Device::new(true, true), Variant::Aht10);
+        sensor.i2c.crc_offset = 1;
+        assert_eq!(sensor.measure().unwrap(), (25.0, 50.0));
+    }
+
+    #[test]
+    fn initializes_aht2x_only_when_uncalibrated() {
+        let mut sensor = sensor(Device::new(true, true), Variant::Aht2x);
+        sensor.init().unwrap();
+        assert!(commands(&sensor).is_empty());
+        let mut sensor = self::sensor(Device::new(false, true), Variant::Aht2x);
+        sensor.init().unwrap();
+        assert_eq!(commands(&sensor), [CMD_INIT_AHT2X[0]]);
+    }
 }
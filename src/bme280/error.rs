@@ -0,0 +1,20 @@
+use snafu::Snafu;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum Error<I2CError>
+where
+    I2CError: 'static + std::error::Error,
+{
+    #[snafu(display("BME280 read error"))]
+    ReadError { source: I2CError },
+
+    #[snafu(display("BME280 write error"))]
+    WriteError { source: I2CError },
+
+    #[snafu(display("Unknown chip id {:#04x}, BME280 or BMP280 is expected", id))]
+    ChipIdError { id: u8 },
+
+    #[snafu(display("BME280 measurement has not finished in time"))]
+    TimeoutError {},
+}
@@ -0,0 +1,423 @@
+pub mod error;
+
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use error::Error;
+use snafu::ResultExt;
+
+pub const I2C_ADDRESS: u8 = 0x76; // SDO to GND, 0x77 when it is pulled up
+
+const REG_CALIBRATION_TP: u8 = 0x88; // 26 bytes of temperature and pressure coefficients, H1 at the end
+const REG_CHIP_ID: u8 = 0xD0;
+const REG_RESET: u8 = 0xE0;
+const REG_CALIBRATION_H: u8 = 0xE1; // 7 bytes of humidity coefficients
+const REG_CTRL_HUM: u8 = 0xF2;
+const REG_STATUS: u8 = 0xF3;
+const REG_CTRL_MEAS: u8 = 0xF4;
+const REG_CONFIG: u8 = 0xF5;
+const REG_DATA: u8 = 0xF7; // pressure, temperature and humidity (BME280 only)
+
+const CHIP_ID_BME280: u8 = 0x60;
+const CHIP_IDS_BMP280: [u8; 3] = [0x56, 0x57, 0x58];
+const RESET: u8 = 0xB6;
+
+const MEASURE_POLL_MS: u32 = 2;
+const MEASURE_TIMEOUT_MS: u32 = 200;
+
+bitflags! {
+    struct StatusFlags: u8 {
+        const MEASURING = (1 << 3); // 1 - conversion is running
+        const IM_UPDATE = (1 << 0); // 1 - calibration is being copied from NVM
+    }
+}
+
+/// Number of samples averaged for each measurement, `Skip` turns the measurement off
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Oversampling {
+    Skip = 0,
+    X1 = 1,
+    X2 = 2,
+    X4 = 3,
+    X8 = 4,
+    X16 = 5,
+}
+
+impl Oversampling {
+    pub fn from_samples(samples: u8) -> Option<Self> {
+        Some(match samples {
+            0 => Oversampling::Skip,
+            1 => Oversampling::X1,
+            2 => Oversampling::X2,
+            4 => Oversampling::X4,
+            8 => Oversampling::X8,
+            16 => Oversampling::X16,
+            _ => return None,
+        })
+    }
+}
+
+/// IIR filter coefficient smoothing pressure and temperature
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Off = 0,
+    X2 = 1,
+    X4 = 2,
+    X8 = 3,
+    X16 = 4,
+}
+
+impl Filter {
+    pub fn from_coefficient(coefficient: u8) -> Option<Self> {
+        Some(match coefficient {
+            0 | 1 => Filter::Off,
+            2 => Filter::X2,
+            4 => Filter::X4,
+            8 => Filter::X8,
+            16 => Filter::X16,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// one measurement on request, the sensor sleeps in between
+    Forced,
+    /// continuous measurements, 0.5 ms standby between them
+    Normal,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub temperature: Oversampling,
+    pub pressure: Oversampling,
+    /// ignored by BMP280
+    pub humidity: Oversampling,
+    pub filter: Filter,
+    pub mode: Mode,
+}
+
+/// Factory compensation coefficients
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Calibration {
+    pub t1: u16,
+    pub t2: i16,
+    pub t3: i16,
+    pub p1: u16,
+    pub p2: i16,
+    pub p3: i16,
+    pub p4: i16,
+    pub p5: i16,
+    pub p6: i16,
+    pub p7: i16,
+    pub p8: i16,
+    pub p9: i16,
+    pub h1: u8,
+    pub h2: i16,
+    pub h3: u8,
+    pub h4: i16,
+    pub h5: i16,
+    pub h6: i8,
+}
+
+impl Calibration {
+    /// Decodes the coefficients from the 0x88..=0xA1 and 0xE1..=0xE7 register blocks
+    pub fn decode(tp: &[u8; 26], h: &[u8; 7]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([tp[i], tp[i + 1]]);
+        let i16_at = |i: usize| i16::from_le_bytes([tp[i], tp[i + 1]]);
+        Calibration {
+            t1: u16_at(0),
+            t2: i16_at(2),
+            t3: i16_at(4),
+            p1: u16_at(6),
+            p2: i16_at(8),
+            p3: i16_at(10),
+            p4: i16_at(12),
+            p5: i16_at(14),
+            p6: i16_at(16),
+            p7: i16_at(18),
+            p8: i16_at(20),
+            p9: i16_at(22),
+            h1: tp[25],
+            h2: i16::from_le_bytes([h[0], h[1]]),
+            h3: h[2],
+            // 12-bit values sharing the middle byte
+            h4: ((h[3] as i8 as i16) << 4) | (h[4] & 0x0F) as i16,
+            h5: ((h[5] as i8 as i16) << 4) | (h[4] >> 4) as i16,
+            h6: h[6] as i8,
+        }
+    }
+
+    /// Temperature in celsius and the fine temperature used by the other compensations (datasheet 8.1)
+    pub fn temperature(&self, adc: u32) -> (f64, f64) {
+        let adc = adc as f64;
+        let var1 = (adc / 16384.0 - self.t1 as f64 / 1024.0) * self.t2 as f64;
+        let var2 = (adc / 131072.0 - self.t1 as f64 / 8192.0).powi(2) * self.t3 as f64;
+        let fine = var1 + var2;
+        (fine / 5120.0, fine)
+    }
+
+    /// Pressure in Pa
+    pub fn pressure(&self, adc: u32, fine: f64) -> f64 {
+        let mut var1 = fine / 2.0 - 64000.0;
+        let mut var2 = var1 * var1 * self.p6 as f64 / 32768.0;
+        var2 += var1 * self.p5 as f64 * 2.0;
+        var2 = var2 / 4.0 + self.p4 as f64 * 65536.0;
+        var1 = (self.p3 as f64 * var1 * var1 / 524288.0 + self.p2 as f64 * var1) / 524288.0;
+        var1 = (1.0 + var1 / 32768.0) * self.p1 as f64;
+        if var1 == 0.0 {
+            // avoids division by zero
+            return 0.0;
+        }
+        let mut pressure = 1048576.0 - adc as f64;
+        pressure = (pressure - var2 / 4096.0) * 6250.0 / var1;
+        let var1 = self.p9 as f64 * pressure * pressure / 2147483648.0;
+        let var2 = pressure * self.p8 as f64 / 32768.0;
+        pressure + (var1 + var2 + self.p7 as f64) / 16.0
+    }
+
+    /// Relative humidity, 0-100%
+    pub fn humidity(&self, adc: u16, fine: f64) -> f64 {
+        let var = fine - 76800.0;
+        let var = (adc as f64 - (self.h4 as f64 * 64.0 + self.h5 as f64 / 16384.0 * var))
+            * (self.h2 as f64 / 65536.0
+                * (1.0 + self.h6 as f64 / 67108864.0 * var * (1.0 + self.h3 as f64 / 67108864.0 * var)));
+        let var = var * (1.0 - self.h1 as f64 * var / 524288.0);
+        var.clamp(0.0, 100.0)
+    }
+}
+
+/// Bosch BME280 (temperature, pressure and humidity) or BMP280 (no humidity) sensor
+pub struct BME280<I2C> {
+    i2c: I2C,
+    delay_ms: fn(u32) -> (),
+    address: u8,
+    calibration: Calibration,
+    settings: Option<Settings>,
+    humidity: bool,
+}
+
+impl<I2C, I2CError> BME280<I2C>
+where
+    I2CError: std::error::Error,
+    I2C: Write<Error = I2CError> + WriteRead<Error = I2CError>,
+{
+    pub fn new(i2c: I2C, address: u8, delay_ms: fn(u32) -> ()) -> Self {
+        BME280 {
+            i2c,
+            delay_ms,
+            address,
+            calibration: Calibration::default(),
+            settings: None,
+            humidity: false,
+        }
+    }
+
+    /// Resets the sensor, reads its calibration and applies the settings
+    pub fn init(&mut self, settings: Settings) -> Result<(), Error<I2CError>> {
+        let mut id = [0; 1];
+        self.read(REG_CHIP_ID, &mut id)?;
+        self.humidity = match id[0] {
+            CHIP_ID_BME280 => true,
+            id if CHIP_IDS_BMP280.contains(&id) => false,
+            id => return Err(Error::ChipIdError { id }),
+        };
+
+        self.write(REG_RESET, RESET)?;
+        (self.delay_ms)(2);
+        self.wait(StatusFlags::IM_UPDATE)?;
+
+        let mut tp = [0; 26];
+        self.read(REG_CALIBRATION_TP, &mut tp)?;
+        let mut h = [0; 7];
+        if self.humidity {
+            self.read(REG_CALIBRATION_H, &mut h)?;
+        }
+        self.calibration = Calibration::decode(&tp, &h);
+
+        // the sleep mode is required to change the config, ctrl_hum is applied by the write to ctrl_meas
+        self.write(REG_CONFIG, (settings.filter as u8) << 2)?;
+        if self.humidity {
+            self.write(REG_CTRL_HUM, settings.humidity as u8)?;
+        }
+        if settings.mode == Mode::Normal {
+            self.write(REG_CTRL_MEAS, ctrl_meas(&settings, 0b11))?;
+        }
+        self.settings = Some(settings);
+        Ok(())
+    }
+
+    /// `false` for BMP280
+    pub fn has_humidity(&self) -> bool {
+        self.humidity
+    }
+
+    /// Measures temperature (celsius), pressure (Pa) and humidity (%, BME280 only)
+    pub fn measure(&mut self) -> Result<(f32, f32, Option<f32>), Error<I2CError>> {
+        let settings = self.settings.unwrap_or(Settings {
+            temperature: Oversampling::X1,
+            pressure: Oversampling::X1,
+            humidity: Oversampling::X1,
+            filter: Filter::Off,
+            mode: Mode::Forced,
+        });
+        if settings.mode == Mode::Forced {
+            self.write(REG_CTRL_MEAS, ctrl_meas(&settings, 0b01))?;
+            (self.delay_ms)(MEASURE_POLL_MS);
+            self.wait(StatusFlags::MEASURING)?;
+        }
+
+        let mut buf = [0; 8];
+        self.read(REG_DATA, &mut buf)?;
+        let pressure = ((buf[0] as u32) << 12) | ((buf[1] as u32) << 4) | ((buf[2] as u32) >> 4);
+        let temperature = ((buf[3] as u32) << 12) | ((buf[4] as u32) << 4) | ((buf[5] as u32) >> 4);
+        let humidity = ((buf[6] as u16) << 8) | buf[7] as u16;
+
+        let (temperature, fine) = self.calibration.temperature(temperature);
+        let pressure = self.calibration.pressure(pressure, fine);
+        let humidity = self.humidity.then(|| self.calibration.humidity(humidity, fine) as f32);
+        Ok((temperature as f32, pressure as f32, humidity))
+    }
+
+    /// Waits until the status flag is cleared
+    fn wait(&mut self, flag: StatusFlags) -> Result<(), Error<I2CError>> {
+        let mut status = [0; 1];
+        for _ in 0..MEASURE_TIMEOUT_MS / MEASURE_POLL_MS {
+            self.read(REG_STATUS, &mut status)?;
+            if !StatusFlags::from_bits_truncate(status[0]).contains(flag) {
+                return Ok(());
+            }
+            (self.delay_ms)(MEASURE_POLL_MS);
+        }
+        Err(Error::TimeoutError {})
+    }
+
+    fn read(&mut self, register: u8, buf: &mut [u8]) -> Result<(), Error<I2CError>> {
+        self.i2c
+            .write_read(self.address, &[register], buf)
+            .context(error::ReadSnafu)
+    }
+
+    fn write(&mut self, register: u8, value: u8) -> Result<(), Error<I2CError>> {
+        self.i2c
+            .write(self.address, &[register, value])
+            .context(error::WriteSnafu)
+    }
+}
+
+fn ctrl_meas(settings: &Settings, mode: u8) -> u8 {
+    ((settings.temperature as u8) << 5) | ((settings.pressure as u8) << 2) | mode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    /// Registers of the sensor behind the I2C bus
+    struct Registers([u8; 256]);
+
+    impl WriteRead for Registers {
+        type Error = Infallible;
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Infallible> {
+            assert_eq!(address, I2C_ADDRESS);
+            let start = bytes[0] as usize;
+            buffer.copy_from_slice(&self.0[start..start + buffer.len()]);
+            Ok(())
+        }
+    }
+
+    impl Write for Registers {
+        type Error = Infallible;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Infallible> {
+            assert_eq!(address, I2C_ADDRESS);
+            self.0[bytes[0] as usize] = bytes[1];
+            Ok(())
+        }
+    }
+
+    /// Trimming parameters and ADC values of the datasheet example (BMP280 datasheet 3.12),
+    /// humidity ones are typical for BME280
+    fn sensor(chip_id: u8) -> BME280<Registers> {
+        let mut registers = [0; 256];
+        registers[REG_CHIP_ID as usize] = chip_id;
+
+        let tp: [i32; 12] = [
+            27504, 26435, -1000, 36477, -10685, 3024, 2855, 140, -7, 15500, -14600, 6000,
+        ];
+        for (i, value) in tp.iter().enumerate() {
+            let at = REG_CALIBRATION_TP as usize + i * 2;
+            registers[at..at + 2].copy_from_slice(&(*value as u16).to_le_bytes());
+        }
+        // H1 = 75, H2 = 362, H3 = 0, H4 = 313, H5 = 50, H6 = 30
+        registers[REG_CALIBRATION_TP as usize + 25] = 75;
+        let h = [0x6A, 0x01, 0x00, 0x13, 0x29, 0x03, 0x1E];
+        registers[REG_CALIBRATION_H as usize..REG_CALIBRATION_H as usize + 7].copy_from_slice(&h);
+
+        // pressure 415148, temperature 519888, humidity 30000
+        let data = [0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00, 0x75, 0x30];
+        registers[REG_DATA as usize..REG_DATA as usize + 8].copy_from_slice(&data);
+        BME280::new(Registers(registers), I2C_ADDRESS, |_| {})
+    }
+
+    fn settings(mode: Mode) -> Settings {
+        Settings {
+            temperature: Oversampling::X2,
+            pressure: Oversampling::X16,
+            humidity: Oversampling::X1,
+            filter: Filter::X4,
+            mode,
+        }
+    }
+
+    #[test]
+    fn decodes_calibration() {
+        let mut sensor = sensor(CHIP_ID_BME280);
+        sensor.init(settings(Mode::Forced)).unwrap();
+        let calibration = sensor.calibration;
+        assert_eq!((calibration.t1, calibration.t2, calibration.t3), (27504, 26435, -1000));
+        assert_eq!((calibration.p1, calibration.p8, calibration.p9), (36477, -14600, 6000));
+        assert_eq!((calibration.h1, calibration.h2, calibration.h3), (75, 362, 0));
+        assert_eq!((calibration.h4, calibration.h5, calibration.h6), (313, 50, 30));
+    }
+
+    #[test]
+    fn compensates_datasheet_example() {
+        let mut sensor = sensor(CHIP_ID_BME280);
+        sensor.init(settings(Mode::Forced)).unwrap();
+        let (temperature, pressure, humidity) = sensor.measure().unwrap();
+        assert!((temperature - 25.08).abs() < 0.01, "{}", temperature);
+        assert!((pressure - 100653.27).abs() < 0.1, "{}", pressure);
+        // 54.997% by the integer compensation of the BME280 datasheet
+        assert!((humidity.unwrap() - 55.0).abs() < 0.01, "{:?}", humidity);
+
+        // forced measurement is started with x2 temperature and x16 pressure oversampling
+        assert_eq!(ctrl_meas(&settings(Mode::Forced), 0b01), 0b0101_0101);
+        assert_eq!(
+            sensor.i2c.0[REG_CTRL_MEAS as usize],
+            ctrl_meas(&settings(Mode::Forced), 0b01)
+        );
+        assert_eq!(sensor.i2c.0[REG_CTRL_HUM as usize], 1);
+        assert_eq!(sensor.i2c.0[REG_CONFIG as usize], 2 << 2);
+    }
+
+    #[test]
+    fn bmp280_has_no_humidity() {
+        let mut sensor = sensor(0x58);
+        sensor.init(settings(Mode::Normal)).unwrap();
+        assert!(!sensor.has_humidity());
+        assert_eq!(
+            sensor.i2c.0[REG_CTRL_MEAS as usize],
+            ctrl_meas(&settings(Mode::Normal), 0b11)
+        );
+        let (temperature, _, humidity) = sensor.measure().unwrap();
+        assert!((temperature - 25.08).abs() < 0.01);
+        assert_eq!(humidity, None);
+
+        assert!(matches!(
+            self::sensor(0x42).init(settings(Mode::Forced)),
+            Err(Error::ChipIdError { id: 0x42 })
+        ));
+    }
+}
@@ -0,0 +1,38 @@
+use snafu::ResultExt;
+use sysfs_pwm::Pwm;
+
+use crate::{
+    error::{self, Error},
+    model,
+};
+
+/// Piezo buzzer driven by a sysfs PWM channel
+pub(crate) struct Buzzer {
+    pwm: Pwm,
+    on: bool,
+}
+
+impl Buzzer {
+    /// Exports the PWM channel and configures the tone, the buzzer stays silent.
+    pub fn new(config: &model::Buzzer) -> Result<Self, Error> {
+        let pwm = Pwm::new(config.chip, config.pwm).context(error::PwmSnafu)?;
+        pwm.export().context(error::PwmSnafu)?;
+
+        let period = 1_000_000_000 / config.frequency.max(1); // nanoseconds
+        pwm.set_period_ns(period).context(error::PwmSnafu)?;
+        pwm.set_duty_cycle_ns(period / 100 * config.duty.min(100))
+            .context(error::PwmSnafu)?;
+        pwm.enable(false).context(error::PwmSnafu)?;
+
+        Ok(Buzzer { pwm, on: false })
+    }
+
+    /// Turns the sound on or off, does nothing if the buzzer is already in the requested state
+    pub fn set(&mut self, on: bool) -> Result<(), Error> {
+        if self.on != on {
+            self.pwm.enable(on).context(error::PwmSnafu)?;
+            self.on = on;
+        }
+        Ok(())
+    }
+}
@@ -1,25 +1,37 @@
-use chrono::Timelike;
+use chrono::{DateTime, Local, Timelike};
 
 use crate::{
     display::{self, LinearMatrixDisplay},
     error::Error,
 };
 
-pub(crate) fn draw(display: &mut LinearMatrixDisplay, draw_dots: bool, slim: bool) -> Result<(), Error> {
-    let time = chrono::Local::now();
-    let hours = time.hour();
-    let minutes = time.minute();
+pub(crate) fn draw(
+    display: &mut LinearMatrixDisplay,
+    time: &DateTime<Local>,
+    draw_dots: bool,
+    slim: bool,
+) -> Result<(), Error> {
+    draw_pair(display, time.hour(), time.minute(), draw_dots, slim)
+}
 
+/// Draws two 2-digit numbers separated by semicolon using the big digits: `HH:MM` or `MM:SS`
+pub(crate) fn draw_pair(
+    display: &mut LinearMatrixDisplay,
+    left: u32,
+    right: u32,
+    draw_dots: bool,
+    slim: bool,
+) -> Result<(), Error> {
     let (semicolon, nums) = if slim {
         (display::SLIM_SEMICOLON, display::SLIM_NUMS)
     } else {
         (display::SEMICOLON, display::NUMS)
     };
 
-    let h1 = nums[(hours / 10) as usize];
-    let h2 = nums[(hours % 10) as usize];
-    let m1 = nums[(minutes / 10) as usize];
-    let m2 = nums[(minutes % 10) as usize];
+    let h1 = nums[(left / 10 % 10) as usize];
+    let h2 = nums[(left % 10) as usize];
+    let m1 = nums[(right / 10 % 10) as usize];
+    let m2 = nums[(right % 10) as usize];
 
     display.draw(|x, y| {
         if x >= 1 && x <= 6 {
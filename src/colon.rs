@@ -0,0 +1,82 @@
+use std::f32::consts::PI;
+
+use crate::model::ColonMode;
+
+/// Frames per half a second used to dither the pulsing colon.
+/// Every frame redraws the whole display, so it is limited by the bit-banged SPI speed.
+const PULSE_FRAMES: usize = 10;
+
+/// Decides when the colon is lit. The MAX7219 has one brightness for the whole matrix,
+/// so the pulse is done with temporal dithering: the colon is lit in a part of frames.
+pub(crate) struct Colon {
+    /// brightness accumulated but not shown yet
+    error: f32,
+}
+
+impl Colon {
+    pub fn new() -> Self {
+        Colon { error: 0.0 }
+    }
+
+    /// Colon state for each of equal frames of the half a second, a single frame for modes without pulse
+    pub fn frames(&mut self, mode: ColonMode, first_half: bool, synchronized: bool) -> Vec<bool> {
+        match mode {
+            ColonMode::Steady => vec![true],
+            ColonMode::Blink => vec![first_half],
+            ColonMode::BlinkUnsynced => vec![synchronized || first_half],
+            ColonMode::Pulse => (0..PULSE_FRAMES)
+                .map(|frame| {
+                    // the brightest at the beginning of a second, dark in the middle of it
+                    let phase = frame as f32 / (2 * PULSE_FRAMES) as f32 + if first_half { 0.0 } else { 0.5 };
+                    self.error += (1.0 + (2.0 * PI * phase).cos()) / 2.0;
+                    let lit = self.error >= 0.5;
+                    if lit {
+                        self.error -= 1.0;
+                    }
+                    lit
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// frames of one second: the first half, then the second one
+    fn period(colon: &mut Colon, mode: ColonMode, synchronized: bool) -> Vec<bool> {
+        let mut frames = colon.frames(mode, true, synchronized);
+        frames.extend(colon.frames(mode, false, synchronized));
+        frames
+    }
+
+    #[test]
+    fn blinks() {
+        let mut colon = Colon::new();
+        assert_eq!(period(&mut colon, ColonMode::Steady, false), [true, true]);
+        assert_eq!(period(&mut colon, ColonMode::Blink, true), [true, false]);
+        assert_eq!(period(&mut colon, ColonMode::Blink, false), [true, false]);
+    }
+
+    #[test]
+    fn blinks_only_unsynchronized() {
+        let mut colon = Colon::new();
+        assert_eq!(period(&mut colon, ColonMode::BlinkUnsynced, true), [true, true]);
+        assert_eq!(period(&mut colon, ColonMode::BlinkUnsynced, false), [true, false]);
+    }
+
+    #[test]
+    fn pulses() {
+        let mut colon = Colon::new();
+        let frames = period(&mut colon, ColonMode::Pulse, true);
+        assert_eq!(frames.len(), 2 * PULSE_FRAMES);
+        // bright around the beginning of the second, dark in the middle, lit in half of the frames
+        let pattern = [1, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1];
+        assert_eq!(frames.iter().map(|&lit| lit as u8).collect::<Vec<_>>(), pattern);
+        // the dithering error does not drift over the seconds
+        for _ in 0..10 {
+            assert_eq!(period(&mut colon, ColonMode::Pulse, false), frames);
+        }
+    }
+}
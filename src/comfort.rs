@@ -0,0 +1,185 @@
+/// Magnus formula coefficients (Sonntag, 1990), good within ±0.35°C from -45°C to 60°C
+const MAGNUS_A: f32 = 17.62;
+const MAGNUS_B: f32 = 243.12;
+/// hPa, saturation vapour pressure at 0°C
+const MAGNUS_C: f32 = 6.112;
+/// g·K/J, molar mass of water over the gas constant
+const WATER_VAPOUR_CONSTANT: f32 = 2.1674;
+
+/// Relative humidity below which the air is dry
+const DRY_HUMIDITY: f32 = 30.0;
+/// Relative humidity above which the air feels humid
+const HUMID_HUMIDITY: f32 = 60.0;
+/// Dew point above which the air feels humid whatever the relative humidity is
+const HUMID_DEW_POINT: f32 = 16.0;
+/// Relative humidity mould grows at on the walls, which are a bit colder than the air
+const MOULD_HUMIDITY: f32 = 70.0;
+
+/// How the air feels indoors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Comfort {
+    Dry,
+    Comfortable,
+    Humid,
+    MouldRisk,
+}
+
+/// Celsius the air has to be cooled to for the dew to fall
+pub(crate) fn dew_point(temperature: f32, humidity: f32) -> f32 {
+    let gamma = (humidity.max(1.0) / 100.0).ln() + MAGNUS_A * temperature / (MAGNUS_B + temperature);
+    MAGNUS_B * gamma / (MAGNUS_A - gamma)
+}
+
+/// Grams of water in a cubic metre of the air
+pub(crate) fn absolute_humidity(temperature: f32, humidity: f32) -> f32 {
+    let saturation = MAGNUS_C * (MAGNUS_A * temperature / (MAGNUS_B + temperature)).exp();
+    saturation * humidity * WATER_VAPOUR_CONSTANT / (273.15 + temperature)
+}
+
+/// Apparent temperature in celsius by the US National Weather Service: the Steadman approximation
+/// for the mild weather, the Rothfusz regression with its adjustments above 80°F
+pub(crate) fn heat_index(temperature: f32, humidity: f32) -> f32 {
+    let t = temperature * 9.0 / 5.0 + 32.0;
+    let rh = humidity;
+    let simple = 0.5 * (t + 61.0 + (t - 68.0) * 1.2 + rh * 0.094);
+    let fahrenheit = if (simple + t) / 2.0 < 80.0 {
+        simple
+    } else {
+        let mut index = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+            - 0.224_755_4 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+        if rh < 13.0 && (80.0..=112.0).contains(&t) {
+            index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+        } else if rh > 85.0 && (80.0..=87.0).contains(&t) {
+            index += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+        }
+        index
+    };
+    (fahrenheit - 32.0) * 5.0 / 9.0
+}
+
+/// Canadian humidex, how hot the humid weather feels, it has no unit but is close to celsius
+pub(crate) fn humidex(temperature: f32, humidity: f32) -> f32 {
+    let dew_point = dew_point(temperature, humidity);
+    // hPa, vapour pressure by the formula of Environment Canada
+    let vapour = 6.11 * (5417.753 * (1.0 / 273.16 - 1.0 / (273.15 + dew_point))).exp();
+    temperature + 0.5555 * (vapour - 10.0)
+}
+
+pub(crate) fn comfort(temperature: f32, humidity: f32) -> Comfort {
+    if humidity >= MOULD_HUMIDITY {
+        Comfort::MouldRisk
+    } else if humidity > HUMID_HUMIDITY || dew_point(temperature, humidity) >= HUMID_DEW_POINT {
+        Comfort::Humid
+    } else if humidity < DRY_HUMIDITY {
+        Comfort::Dry
+    } else {
+        Comfort::Comfortable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f32, expected: f32, tolerance: f32, what: &str) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{}: {} instead of {}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    fn celsius(fahrenheit: f32) -> f32 {
+        (fahrenheit - 32.0) * 5.0 / 9.0
+    }
+
+    /// Relative humidity of the air with the dew point by the same Magnus formula
+    fn humidity(temperature: f32, dew_point: f32) -> f32 {
+        let magnus = |t: f32| MAGNUS_A * t / (MAGNUS_B + t);
+        100.0 * (magnus(dew_point) - magnus(temperature)).exp()
+    }
+
+    #[test]
+    fn dew_point_table() {
+        for (temperature, humidity, expected) in [
+            (25.0, 50.0, 13.9),
+            (20.0, 80.0, 16.4),
+            (30.0, 30.0, 10.5),
+            (0.0, 100.0, 0.0),
+        ] {
+            let what = format!("dew point at {}°C {}%", temperature, humidity);
+            assert_near(dew_point(temperature, humidity), expected, 0.2, &what);
+        }
+    }
+
+    #[test]
+    fn absolute_humidity_table() {
+        // saturated air holds 4.85, 17.3 and 30.4 g/m³
+        for (temperature, humidity, expected) in [
+            (0.0, 100.0, 4.85),
+            (20.0, 100.0, 17.3),
+            (30.0, 100.0, 30.4),
+            (25.0, 50.0, 11.5),
+        ] {
+            let what = format!("absolute humidity at {}°C {}%", temperature, humidity);
+            assert_near(absolute_humidity(temperature, humidity), expected, 0.2, &what);
+        }
+    }
+
+    #[test]
+    fn heat_index_table() {
+        // NWS heat index chart in °F, its values are rounded
+        for (temperature, humidity, expected) in [
+            (80.0, 40.0, 80.0),
+            (90.0, 50.0, 95.0),
+            (100.0, 40.0, 109.0),
+            (96.0, 65.0, 121.0),
+            (86.0, 90.0, 105.0),
+        ] {
+            let what = format!("heat index at {}°F {}%", temperature, humidity);
+            assert_near(
+                heat_index(celsius(temperature), humidity),
+                celsius(expected),
+                // a degree of fahrenheit
+                5.0 / 9.0,
+                &what,
+            );
+        }
+        // the mild weather is close to the air temperature, Steadman gives 66.9°F at 68°F 50%
+        assert_near(heat_index(20.0, 50.0), celsius(66.9), 0.1, "heat index at 20°C");
+    }
+
+    #[test]
+    fn humidex_table() {
+        // Environment Canada humidex table by the temperature and the dew point
+        for (temperature, dew_point, expected) in [(25.0, 15.0, 29.0), (30.0, 20.0, 37.0), (35.0, 25.0, 47.0)] {
+            let what = format!("humidex at {}°C, dew point {}°C", temperature, dew_point);
+            assert_near(
+                humidex(temperature, humidity(temperature, dew_point)),
+                expected,
+                1.0,
+                &what,
+            );
+        }
+    }
+
+    #[test]
+    fn comfort_boundaries() {
+        assert_eq!(comfort(20.0, DRY_HUMIDITY - 0.1), Comfort::Dry);
+        assert_eq!(comfort(20.0, DRY_HUMIDITY), Comfort::Comfortable);
+        assert_eq!(comfort(20.0, HUMID_HUMIDITY), Comfort::Comfortable);
+        assert_eq!(comfort(20.0, HUMID_HUMIDITY + 0.1), Comfort::Humid);
+        assert_eq!(comfort(20.0, MOULD_HUMIDITY - 0.1), Comfort::Humid);
+        assert_eq!(comfort(20.0, MOULD_HUMIDITY), Comfort::MouldRisk);
+        // warm air is humid below the humidity limit, dew point 15.3°C and 16.3°C
+        assert_eq!(comfort(25.0, 55.0), Comfort::Comfortable);
+        assert_eq!(comfort(26.0, 55.0), Comfort::Humid);
+    }
+}
@@ -0,0 +1,267 @@
+use snafu::ResultExt;
+use std::{fmt, fs, path::Path, time::Instant};
+
+use crate::{
+    display::DutyMeter,
+    error::{self, Error},
+    logger, model,
+};
+
+/// Heat sources of the enclosure at the moment of a measurement
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HeatLoad {
+    /// display duty averaged over the thermal time constant, 0.0 - 1.0
+    pub display: f32,
+    /// celsius
+    pub cpu: f32,
+}
+
+impl fmt::Display for HeatLoad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "display {:.0}%, CPU {:.1}°C", self.display * 100.0, self.cpu)
+    }
+}
+
+/// Estimates how much the sensor is heated by the display and the SoC next to it
+pub(crate) struct Compensation {
+    config: model::Compensation,
+    duty: DutyMeter,
+    /// exponential moving average of the display duty
+    display: Option<(f32, Instant)>,
+}
+
+impl Compensation {
+    pub fn new(config: &model::Compensation, duty: DutyMeter) -> Self {
+        Compensation { config: config.clone(), duty, display: None }
+    }
+
+    /// Samples the heat sources, the enclosure warms up slowly so the display duty is averaged
+    pub fn load(&mut self) -> Result<HeatLoad, Error> {
+        let duty = self.duty.duty();
+        let now = Instant::now();
+        let display = match self.display {
+            Some((average, time)) => {
+                let elapsed = now.duration_since(time).as_secs_f32();
+                let time_constant = self.config.time_constant_sec.max(1) as f32;
+                average + (duty - average) * (1.0 - (-elapsed / time_constant).exp())
+            }
+            None => duty,
+        };
+        self.display = Some((display, now));
+        Ok(HeatLoad {
+            display,
+            cpu: cpu_temperature(&self.config.thermal_zone)?,
+        })
+    }
+
+    /// Sensor temperature without the self-heating
+    pub fn apply(&self, temperature: f32, load: &HeatLoad) -> f32 {
+        temperature - heating(&self.config, temperature, load)
+    }
+}
+
+/// Celsius the sensor reads above the air temperature
+fn heating(config: &model::Compensation, temperature: f32, load: &HeatLoad) -> f32 {
+    config.display * load.display + config.cpu * (load.cpu - temperature) + config.offset
+}
+
+/// Temperature of the thermal zone, e.g. `/sys/class/thermal/thermal_zone0/temp`
+pub(crate) fn cpu_temperature(path: &str) -> Result<f32, Error> {
+    let millidegrees: i32 = fs::read_to_string(path)
+        .context(error::ThermalZoneSnafu { path })?
+        .trim()
+        .parse()
+        .map_err(|_| Error::ThermalZoneDataError { path: path.to_string() })?;
+    Ok(millidegrees as f32 / 1000.0)
+}
+
+/// Measurement next to the reference thermometer
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Sample {
+    pub temperature: f32,
+    pub load: HeatLoad,
+    pub reference: f32,
+}
+
+/// Reads the samples from CSV with `temperature`, `display`, `cpu` and `reference` columns in any order,
+/// rows missing any of them are skipped
+pub(crate) fn read_log(path: &Path) -> Result<Vec<Sample>, Error> {
+    let text = fs::read_to_string(path).context(error::CompensationLogSnafu)?;
+    let mut lines = text.lines();
+    let header = logger::split(lines.next().unwrap_or_default());
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column.trim() == name)
+            .ok_or(Error::CompensationColumnError { name: name.to_string() })
+    };
+    let columns = [
+        column("temperature")?,
+        column("display")?,
+        column("cpu")?,
+        column("reference")?,
+    ];
+
+    Ok(lines
+        .filter_map(|line| {
+            let values = logger::split(line);
+            let value = |i: usize| values.get(columns[i])?.trim().parse::<f32>().ok();
+            Some(Sample {
+                temperature: value(0)?,
+                load: HeatLoad { display: value(1)?, cpu: value(2)? },
+                reference: value(3)?,
+            })
+        })
+        .collect())
+}
+
+/// Least squares fit of the coefficients, the other settings are kept
+pub(crate) fn fit(samples: &[Sample], config: &model::Compensation) -> Result<model::Compensation, Error> {
+    // normal equations of `error = display * d + cpu * (c - t) + offset`
+    let mut matrix = [[0.0f64; 4]; 3];
+    for sample in samples {
+        let row = [
+            sample.load.display as f64,
+            (sample.load.cpu - sample.temperature) as f64,
+            1.0,
+        ];
+        let error = (sample.temperature - sample.reference) as f64;
+        for i in 0..3 {
+            for j in 0..3 {
+                matrix[i][j] += row[i] * row[j];
+            }
+            matrix[i][3] += row[i] * error;
+        }
+    }
+    let [display, cpu, offset] = solve(matrix).ok_or(Error::CompensationFitError { samples: samples.len() })?;
+    Ok(model::Compensation {
+        display: display as f32,
+        cpu: cpu as f32,
+        offset: offset as f32,
+        ..config.clone()
+    })
+}
+
+/// Root mean square of the differences from the reference, compensated with the config
+pub(crate) fn rms_error(samples: &[Sample], config: &model::Compensation) -> f32 {
+    let sum = samples
+        .iter()
+        .map(|sample| {
+            (sample.temperature - heating(config, sample.temperature, &sample.load) - sample.reference).powi(2)
+        })
+        .sum::<f32>();
+    (sum / samples.len().max(1) as f32).sqrt()
+}
+
+/// Gaussian elimination with partial pivoting, `None` when the inputs do not vary enough
+fn solve(mut matrix: [[f64; 4]; 3]) -> Option<[f64; 3]> {
+    for i in 0..3 {
+        let pivot = (i..3).max_by(|a, b| matrix[*a][i].abs().total_cmp(&matrix[*b][i].abs()))?;
+        if matrix[pivot][i].abs() < 1e-9 {
+            return None;
+        }
+        matrix.swap(i, pivot);
+        let row = matrix[i];
+        for (j, target) in matrix.iter_mut().enumerate() {
+            if j != i {
+                let factor = target[i] / row[i];
+                for (value, pivot) in target.iter_mut().zip(row).skip(i) {
+                    *value -= factor * pivot;
+                }
+            }
+        }
+    }
+    Some([
+        matrix[0][3] / matrix[0][0],
+        matrix[1][3] / matrix[1][1],
+        matrix[2][3] / matrix[2][2],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn fits_known_coefficients() {
+        let known = model::Compensation {
+            display: 1.5,
+            cpu: 0.08,
+            offset: 0.3,
+            ..Default::default()
+        };
+        let mut samples = vec![];
+        for display in [0.0, 0.25, 0.5, 1.0] {
+            for cpu in [40.0, 48.0, 61.0] {
+                for temperature in [18.0, 23.5] {
+                    let load = HeatLoad { display, cpu };
+                    let reference = temperature - heating(&known, temperature, &load);
+                    samples.push(Sample { temperature, load, reference });
+                }
+            }
+        }
+        let fitted = fit(&samples, &model::Compensation::default()).unwrap();
+        assert!((fitted.display - 1.5).abs() < 1e-3, "{}", fitted.display);
+        assert!((fitted.cpu - 0.08).abs() < 1e-4, "{}", fitted.cpu);
+        assert!((fitted.offset - 0.3).abs() < 1e-3, "{}", fitted.offset);
+        assert!(rms_error(&samples, &fitted) < 1e-3);
+        assert!(rms_error(&samples, &model::Compensation::default()) > 0.5);
+    }
+
+    #[test]
+    fn needs_varying_inputs() {
+        assert_eq!(solve([[0.0; 4]; 3]), None);
+        // the second and the third equations are the same
+        assert_eq!(
+            solve([[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 1.0, 2.0], [0.0, 2.0, 2.0, 4.0]]),
+            None
+        );
+        assert_eq!(
+            solve([[2.0, 0.0, 0.0, 2.0], [0.0, 0.0, 4.0, 2.0], [0.0, 1.0, 0.0, 3.0]]),
+            Some([1.0, 3.0, 0.5])
+        );
+
+        // the display has never been on
+        let samples = [40.0, 50.0, 60.0].map(|cpu| Sample {
+            temperature: 22.0,
+            load: HeatLoad { display: 0.0, cpu },
+            reference: 21.0,
+        });
+        assert!(matches!(
+            fit(&samples, &model::Compensation::default()),
+            Err(Error::CompensationFitError { samples: 3 })
+        ));
+    }
+
+    #[test]
+    fn reads_columns_in_any_order() {
+        let mut log = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            log,
+            "time,reference,cpu,display,temperature\n\
+             2022-01-15T10:00:00,21.0,45.5,0.5,23.0\n\
+             2022-01-15T10:01:00,21.0,,0.5,23.0\n\
+             2022-01-15T10:02:00,21.5,46.0,1.0\n\
+             2022-01-15T10:03:00,21.5,46.0,1.0,24.0\n"
+        )
+        .unwrap();
+        let samples = read_log(log.path()).unwrap();
+        // the rows with missing values are skipped
+        assert_eq!(samples.len(), 2);
+        let sample = samples[0];
+        assert_eq!((sample.temperature, sample.reference), (23.0, 21.0));
+        assert_eq!((sample.load.display, sample.load.cpu), (0.5, 45.5));
+        assert_eq!(samples[1].temperature, 24.0);
+    }
+
+    #[test]
+    fn requires_all_columns() {
+        let mut log = tempfile::NamedTempFile::new().unwrap();
+        write!(log, "temperature,display,cpu\n23.0,0.5,45.5\n").unwrap();
+        assert!(matches!(
+            read_log(log.path()),
+            Err(Error::CompensationColumnError { name }) if name == "reference"
+        ));
+    }
+}
@@ -0,0 +1,166 @@
+use gpio_cdev::{Chip, EventRequestFlags, LineRequestFlags};
+use snafu::ResultExt;
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    sync::mpsc::{self, Receiver, Sender, SyncSender},
+    thread,
+};
+
+use crate::{
+    error::{self, Error},
+    model,
+};
+
+/// Buttons bounce, presses closer than this are ignored
+const DEBOUNCE_NS: u64 = 200_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Action {
+    Start,
+    Pause,
+    Toggle,
+    Reset,
+    Lap,
+    Stop,
+}
+
+/// Command received from the control socket or a push button.
+/// Text form is `<mode> <action> [argument]`, e.g. `timer start tea` or `stopwatch lap`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Command {
+    /// `duration` is a preset name or a duration like `90`, `3:00` or `1:30:00`
+    Timer {
+        action: Action,
+        duration: Option<String>,
+    },
+    Stopwatch {
+        action: Action,
+    },
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let unknown = || Error::CommandError { command: line.trim().to_string() };
+        let mut words = line.split_whitespace();
+        let mode = words.next().ok_or_else(unknown)?;
+        let action = match words.next().unwrap_or("toggle") {
+            "start" => Action::Start,
+            "pause" => Action::Pause,
+            "toggle" => Action::Toggle,
+            "reset" => Action::Reset,
+            "lap" => Action::Lap,
+            "stop" => Action::Stop,
+            _ => return Err(unknown()),
+        };
+        let argument = words.next().map(|s| s.to_string());
+        if words.next().is_some() {
+            return Err(unknown());
+        }
+
+        match (mode, action, argument) {
+            ("timer", Action::Lap, _) => Err(unknown()),
+            ("timer", action, duration) => Ok(Command::Timer { action, duration }),
+            ("stopwatch", action, None) => Ok(Command::Stopwatch { action }),
+            _ => Err(unknown()),
+        }
+    }
+}
+
+/// Command with the channel its result goes back through, buttons wait for no result
+pub(crate) struct Request {
+    pub command: Command,
+    reply: Option<SyncSender<Result<(), String>>>,
+}
+
+impl Request {
+    /// Reports the result of the command to the sender
+    pub fn reply(self, result: Result<(), Error>) {
+        if let Some(reply) = self.reply {
+            // the connection might be closed already
+            let _ = reply.send(result.map_err(|e| e.to_string()));
+        }
+    }
+}
+
+/// Starts listening on the control socket and configured buttons.
+/// Received commands are delivered through the returned channel, the listeners live in their own threads.
+pub(crate) fn listen(config: &model::Control) -> Result<Receiver<Request>, Error> {
+    let (tx, rx) = mpsc::channel();
+
+    // socket may be left by the previous run
+    let _ = fs::remove_file(&config.socket);
+    let listener = UnixListener::bind(&config.socket).context(error::ControlSnafu)?;
+    let socket_tx = tx.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = serve(stream, &socket_tx) {
+                eprintln!("Control connection failed: {}", e);
+            }
+        }
+    });
+
+    if !config.buttons.is_empty() {
+        let mut chip = Chip::new(&config.gpio_dev).context(error::ButtonSnafu)?;
+        for button in &config.buttons {
+            let command = Command::parse(&button.command)?;
+            let events = chip
+                .get_line(button.pin)
+                .context(error::ButtonSnafu)?
+                .events(LineRequestFlags::INPUT, EventRequestFlags::FALLING_EDGE, "clock-button")
+                .context(error::ButtonSnafu)?;
+            let button_tx = tx.clone();
+            thread::spawn(move || {
+                let mut last_press = 0;
+                for event in events.flatten() {
+                    if event.timestamp().saturating_sub(last_press) < DEBOUNCE_NS {
+                        continue;
+                    }
+                    last_press = event.timestamp();
+                    let request = Request { command: command.clone(), reply: None };
+                    if button_tx.send(request).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    Ok(rx)
+}
+
+/// Reads commands line by line and answers `ok` or the error text on each of them once the clock has applied it
+fn serve(stream: UnixStream, tx: &Sender<Request>) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let result = Command::parse(&line?).map_err(|e| e.to_string()).and_then(|command| {
+            let (reply, answer) = mpsc::sync_channel(1);
+            tx.send(Request { command, reply: Some(reply) })
+                .map_err(|_| "The clock is not running.".to_string())?;
+            answer
+                .recv()
+                .unwrap_or_else(|_| Err("The clock has dropped the command.".to_string()))
+        });
+        match result {
+            Ok(()) => writeln!(writer, "ok")?,
+            Err(e) => writeln!(writer, "{}", e)?,
+        }
+    }
+    Ok(())
+}
+
+/// Sends the command to the running clock, returns its answer
+pub(crate) fn send(socket: &str, command: &str) -> Result<String, Error> {
+    let mut stream = UnixStream::connect(socket).context(error::ControlSnafu)?;
+    writeln!(stream, "{}", command).context(error::ControlSnafu)?;
+    stream
+        .shutdown(std::net::Shutdown::Write)
+        .context(error::ControlSnafu)?;
+
+    let mut answer = String::new();
+    BufReader::new(stream)
+        .read_line(&mut answer)
+        .context(error::ControlSnafu)?;
+    Ok(answer.trim().to_string())
+}
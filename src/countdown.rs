@@ -0,0 +1,322 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time;
+
+use crate::{
+    display::LinearMatrixDisplay,
+    error::Error,
+    model::{self, Language},
+    text,
+};
+
+/// Delay between frames of the fireworks
+const FRAME_MSEC: u64 = 50;
+/// The event is celebrated only when it is noticed in time, not after a jump of the clock
+const CELEBRATE_WITHIN_SEC: i64 = 60;
+
+/// When the event comes, in local time
+#[derive(Debug, Clone, Copy)]
+enum When {
+    Once(NaiveDateTime),
+    /// month, day and time of the event repeated every year
+    Yearly(u32, u32, NaiveTime),
+}
+
+impl When {
+    /// Parses `YYYY-MM-DD [HH:MM]` or `MM-DD [HH:MM]`
+    fn parse(date: &str) -> Option<When> {
+        let (date, time) = match date.trim().split_once(' ') {
+            Some((date, time)) => (date, NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?),
+            None => (date.trim(), NaiveTime::from_hms(0, 0, 0)),
+        };
+        if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            return Some(When::Once(date.and_time(time)));
+        }
+        let (month, day) = date.split_once('-')?;
+        let (month, day) = (month.parse().ok()?, day.parse().ok()?);
+        // 2000 is a leap year, so February 29 is accepted
+        NaiveDate::from_ymd_opt(2000, month, day)?;
+        Some(When::Yearly(month, day, time))
+    }
+
+    /// The first time the event comes after the given one
+    fn next_after(&self, time: &DateTime<Local>) -> Option<DateTime<Local>> {
+        match *self {
+            When::Once(date) => local(date).filter(|date| date > time),
+            // February 29 may be 8 years away
+            When::Yearly(month, day, at) => (time.year()..=time.year() + 8)
+                .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+                .filter_map(|date| local(date.and_time(at)))
+                .find(|date| date > time),
+        }
+    }
+}
+
+/// Local time zone applied to the date, a time skipped by the DST switch is moved an hour later
+fn local(date: NaiveDateTime) -> Option<DateTime<Local>> {
+    Local
+        .from_local_datetime(&date)
+        .earliest()
+        .or_else(|| Local.from_local_datetime(&(date + Duration::hours(1))).earliest())
+}
+
+/// Countdown to the configured events
+pub(crate) struct Countdown<'a> {
+    events: Vec<(&'a str, When)>,
+    celebrate: std::time::Duration,
+    checked: Option<DateTime<Local>>,
+}
+
+impl<'a> Countdown<'a> {
+    pub fn new(config: &'a model::Countdown) -> Result<Self, Error> {
+        let events = config
+            .events
+            .iter()
+            .map(|event| {
+                When::parse(&event.date)
+                    .map(|when| (event.name.as_str(), when))
+                    .ok_or_else(|| Error::EventDateError { date: event.date.clone() })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Countdown {
+            events,
+            celebrate: std::time::Duration::from_secs(config.celebrate_sec as u64),
+            checked: None,
+        })
+    }
+
+    /// Name of the event which has come since the previous call
+    pub fn reached(&mut self, time: &DateTime<Local>) -> Option<&'a str> {
+        let checked = self.checked.replace(*time)?;
+        self.events
+            .iter()
+            .find(|(_, when)| {
+                when.next_after(&checked)
+                    .is_some_and(|at| at <= *time && *time - at < Duration::seconds(CELEBRATE_WITHIN_SEC))
+            })
+            .map(|(name, _)| *name)
+    }
+
+    /// Time left to the upcoming events, `None` when all of them have passed
+    pub fn text(&self, time: &DateTime<Local>, language: Language) -> Option<String> {
+        let texts = self
+            .events
+            .iter()
+            .filter_map(|(name, when)| {
+                let left = when.next_after(time)? - *time;
+                let (days, hours, minutes) = (left.num_days(), left.num_hours() % 24, left.num_minutes() % 60);
+                Some(match (language, days > 0) {
+                    (Language::En, true) => format!("{}: {}d {}h", name, days, hours),
+                    (Language::En, false) => format!("{}: {}h {}m", name, hours, minutes),
+                    (Language::Ru, true) => format!("{}: {} дн {} ч", name, days, hours),
+                    (Language::Ru, false) => format!("{}: {} ч {} мин", name, hours, minutes),
+                })
+            })
+            .collect::<Vec<_>>();
+        (!texts.is_empty()).then(|| texts.join("   "))
+    }
+
+    /// Fireworks followed by the name of the event
+    pub async fn celebrate(
+        &self,
+        display: &mut LinearMatrixDisplay,
+        name: &str,
+        scroll_msec: u64,
+    ) -> Result<(), Error> {
+        fireworks(display, self.celebrate).await?;
+        text::scroll(display, name, scroll_msec).await
+    }
+}
+
+/// Spark of the fireworks, a rocket until it bursts
+struct Spark {
+    x: f32,
+    y: f32,
+    dx: f32,
+    dy: f32,
+    /// frames left, a rocket bursts when it stops rising
+    life: u32,
+    rocket: bool,
+}
+
+/// Rockets launched from the bottom of the display bursting into falling sparks
+async fn fireworks(display: &mut LinearMatrixDisplay, duration: std::time::Duration) -> Result<(), Error> {
+    const GRAVITY: f32 = 0.02;
+    let width = display.width() as f32;
+    let frames = duration.as_millis() as u64 / FRAME_MSEC;
+    let mut random = Random::new();
+    let mut sparks: Vec<Spark> = vec![];
+
+    for frame in 0..frames {
+        // a new rocket every 0.5..1 second, none at the end to let the last ones fade out
+        if frame + 20 < frames && random.below(15) == 0 {
+            sparks.push(Spark {
+                x: random.below(width as u32) as f32,
+                y: 7.0,
+                dx: 0.0,
+                dy: -0.3 - random.below(15) as f32 / 100.0,
+                life: u32::MAX,
+                rocket: true,
+            });
+        }
+
+        let mut bursts = vec![];
+        for spark in sparks.iter_mut() {
+            spark.x += spark.dx;
+            spark.y += spark.dy;
+            spark.dy += GRAVITY;
+            spark.life = spark.life.saturating_sub(1);
+            if spark.rocket && spark.dy >= 0.0 {
+                spark.life = 0;
+                bursts.push((spark.x, spark.y));
+            }
+        }
+        sparks.retain(|spark| spark.life > 0);
+        for (x, y) in bursts {
+            for i in 0..10 {
+                let angle = i as f32 * std::f32::consts::TAU / 10.0;
+                let speed = 0.2 + random.below(20) as f32 / 100.0;
+                sparks.push(Spark {
+                    x,
+                    y,
+                    dx: angle.cos() * speed,
+                    dy: angle.sin() * speed,
+                    life: 8 + random.below(8),
+                    rocket: false,
+                });
+            }
+        }
+
+        display.draw(|x, y| {
+            sparks
+                .iter()
+                .any(|spark| spark.x.round() as isize == x as isize && spark.y.round() as isize == y as isize)
+                as u8
+        })?;
+        time::sleep(std::time::Duration::from_millis(FRAME_MSEC)).await;
+    }
+    Ok(())
+}
+
+/// Xorshift generator, good enough to scatter the sparks
+struct Random(u32);
+
+impl Random {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.subsec_nanos());
+        Random(seed | 1)
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 % n.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local.ymd(year, month, day).and_hms(hour, minute, second)
+    }
+
+    fn countdown(date: &str) -> model::Countdown {
+        model::Countdown {
+            events: vec![model::Event { name: "party".to_string(), date: date.to_string() }],
+            celebrate_sec: 5,
+        }
+    }
+
+    #[test]
+    fn parses_dates() {
+        let once = NaiveDate::from_ymd(2022, 12, 31);
+        assert!(matches!(When::parse("2022-12-31"), Some(When::Once(date)) if date == once.and_hms(0, 0, 0)));
+        assert!(matches!(When::parse(" 2022-12-31 23:30 "), Some(When::Once(date)) if date == once.and_hms(23, 30, 0)));
+        assert!(matches!(
+            When::parse("02-29 08:15"),
+            Some(When::Yearly(2, 29, time)) if time == NaiveTime::from_hms(8, 15, 0)
+        ));
+        for wrong in ["02-30", "13-01", "2022-02-29", "12-31 24:00", "tomorrow", ""] {
+            assert!(When::parse(wrong).is_none(), "{}", wrong);
+        }
+    }
+
+    #[test]
+    fn once_is_never_repeated() {
+        let when = When::parse("2022-12-31 23:30").unwrap();
+        assert_eq!(
+            when.next_after(&at(2022, 1, 1, 0, 0, 0)),
+            Some(at(2022, 12, 31, 23, 30, 0))
+        );
+        assert_eq!(when.next_after(&at(2022, 12, 31, 23, 30, 0)), None);
+    }
+
+    #[test]
+    fn yearly_crosses_year_boundary() {
+        let new_year = When::parse("01-01").unwrap();
+        assert_eq!(
+            new_year.next_after(&at(2022, 12, 31, 23, 59, 59)),
+            Some(at(2023, 1, 1, 0, 0, 0))
+        );
+        let new_year_eve = When::parse("12-31 23:00").unwrap();
+        assert_eq!(
+            new_year_eve.next_after(&at(2022, 12, 31, 22, 59, 0)),
+            Some(at(2022, 12, 31, 23, 0, 0))
+        );
+        assert_eq!(
+            new_year_eve.next_after(&at(2022, 12, 31, 23, 0, 0)),
+            Some(at(2023, 12, 31, 23, 0, 0))
+        );
+    }
+
+    #[test]
+    fn yearly_skips_to_leap_year() {
+        let leap_day = When::parse("02-29").unwrap();
+        assert_eq!(
+            leap_day.next_after(&at(2022, 3, 1, 0, 0, 0)),
+            Some(at(2024, 2, 29, 0, 0, 0))
+        );
+        assert_eq!(
+            leap_day.next_after(&at(2024, 2, 29, 0, 0, 0)),
+            Some(at(2028, 2, 29, 0, 0, 0))
+        );
+        // 2100 is not a leap year
+        assert_eq!(
+            leap_day.next_after(&at(2096, 3, 1, 0, 0, 0)),
+            Some(at(2104, 2, 29, 0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn reaches_event_once() {
+        let config = countdown("2022-12-31 23:30");
+        let mut countdown = Countdown::new(&config).unwrap();
+        assert_eq!(countdown.reached(&at(2022, 12, 31, 23, 29, 59)), None);
+        assert_eq!(countdown.reached(&at(2022, 12, 31, 23, 30, 0)), Some("party"));
+        assert_eq!(countdown.reached(&at(2022, 12, 31, 23, 30, 1)), None);
+    }
+
+    #[test]
+    fn reaches_event_within_minute() {
+        let config = countdown("2022-12-31 23:30");
+        let mut countdown = Countdown::new(&config).unwrap();
+        countdown.reached(&at(2022, 12, 31, 23, 29, 0));
+        assert_eq!(countdown.reached(&at(2022, 12, 31, 23, 30, 59)), Some("party"));
+
+        // not celebrated after a jump of the clock
+        let mut countdown = Countdown::new(&config).unwrap();
+        countdown.reached(&at(2022, 12, 31, 23, 29, 0));
+        assert_eq!(countdown.reached(&at(2022, 12, 31, 23, 31, 0)), None);
+    }
+
+    #[test]
+    fn rejects_wrong_dates() {
+        assert!(Countdown::new(&countdown("02-30")).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+/// CRC-8 of the Sensirion and Aosong sensors: 0x31 polynomial and 0xFF initial value
+pub(crate) fn crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0xFF;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x31 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_datasheet_example() {
+        assert_eq!(crc8(&[0xBE, 0xEF]), 0x92);
+        assert_eq!(crc8(&[]), 0xFF);
+        assert_eq!(crc8(&[0x00, 0x00]), 0x81);
+    }
+}
@@ -3,10 +3,28 @@ use embedded_hal::digital::v2::OutputPin;
 use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
 use max7219::{connectors::PinConnector, DecodeMode, MAX7219};
 use snafu::ResultExt;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
 
 pub struct LinearMatrixDisplay {
     screen: Max7219,
     number_of_matrices: usize,
+    intensity: u8,
+    lit: usize,
+    duty: Arc<AtomicU32>,
+}
+
+/// Share of the power the display consumes at the moment, readable from other tasks
+#[derive(Clone, Default)]
+pub struct DutyMeter(Arc<AtomicU32>);
+
+impl DutyMeter {
+    /// 0.0 (dark) - 1.0 (all pixels lit at the maximum brightness)
+    pub fn duty(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
 }
 
 impl LinearMatrixDisplay {
@@ -72,6 +90,9 @@ impl LinearMatrixDisplay {
         let mut display = LinearMatrixDisplay {
             screen: max7219,
             number_of_matrices: number_of_matrices,
+            intensity: brightness,
+            lit: 0,
+            duty: Arc::new(AtomicU32::new(0)),
         };
 
         display.clear()?;
@@ -89,9 +110,29 @@ impl LinearMatrixDisplay {
                 .map_err(DataError)
                 .context(error::Max7219DataSnafu)?;
         }
+        self.intensity = intensity;
+        self.update_duty();
         Ok(())
     }
 
+    /// Handle to watch the display duty
+    pub fn duty_meter(&self) -> DutyMeter {
+        DutyMeter(self.duty.clone())
+    }
+
+    fn update_duty(&mut self) {
+        // MAX7219 lights the segments (2 * intensity + 1) / 32 of the time,
+        // the share of the 31/32 at the maximum intensity is (2 * intensity + 1) / 31
+        let intensity = (2 * self.intensity.min(0x0F) as usize + 1) as f32 / 31.0;
+        let duty = self.lit as f32 / (self.width() * 8) as f32 * intensity;
+        self.duty.store(duty.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Number of pixel columns
+    pub fn width(&self) -> usize {
+        self.number_of_matrices * 8
+    }
+
     /// Clears the display
     pub fn clear(self: &mut Self) -> Result<(), Error> {
         for i in 0..self.number_of_matrices {
@@ -100,6 +141,8 @@ impl LinearMatrixDisplay {
                 .map_err(DataError)
                 .context(error::Max7219DataSnafu)?;
         }
+        self.lit = 0;
+        self.update_duty();
         Ok(())
     }
 
@@ -127,6 +170,8 @@ impl LinearMatrixDisplay {
                 .map_err(DataError)
                 .context(error::Max7219DataSnafu)?;
         }
+        self.lit = data.iter().flatten().map(|line| line.count_ones() as usize).sum();
+        self.update_duty();
 
         Ok(())
     }
@@ -187,6 +232,19 @@ pub const PERCENT: [u8; 8] = [
 pub const CELSIUS: [u8; 8] = [
     0b000000, 0b000000, 0b100111, 0b001100, 0b001100, 0b001100, 0b001100, 0b000111,
 ];
+pub const FAHRENHEIT: [u8; 8] = [
+    0b000000, 0b000000, 0b101111, 0b001100, 0b001110, 0b001100, 0b001100, 0b001100,
+];
+pub const KELVIN: [u8; 8] = [
+    0b000000, 0b000000, 0b110011, 0b110110, 0b111100, 0b111100, 0b110110, 0b110011,
+];
+pub const HECTOPASCAL: [u8; 8] = [
+    0b000000, 0b000000, 0b111100, 0b110110, 0b110110, 0b111100, 0b110000, 0b110000,
+];
+pub const MILLIMETRE: [u8; 8] = [
+    0b000000, 0b000000, 0b000000, 0b111110, 0b101010, 0b101010, 0b101010, 0b101010,
+];
+pub const MINUS: [u8; 8] = [0b0000, 0b0000, 0b0000, 0b1111, 0b1111, 0b0000, 0b0000, 0b0000];
 pub const HUMIDITY: [u8; 8] = [
     0b0000000, 0b0000000, 0b0000000, 0b1000000, 0b1000000, 0b1110101, 0b1010101, 0b1010111,
 ];
@@ -234,8 +292,73 @@ pub const SLIM_PERCENT: [u8; 8] = [
 pub const SLIM_CELSIUS: [u8; 8] = [
     0b000000, 0b000000, 0b100111, 0b001000, 0b001000, 0b001000, 0b001000, 0b000111,
 ];
+pub const SLIM_FAHRENHEIT: [u8; 8] = [
+    0b000000, 0b000000, 0b100111, 0b001000, 0b001110, 0b001000, 0b001000, 0b001000,
+];
+pub const SLIM_KELVIN: [u8; 8] = [
+    0b000000, 0b000000, 0b010001, 0b010010, 0b011100, 0b010100, 0b010010, 0b010001,
+];
+pub const SLIM_HECTOPASCAL: [u8; 8] = [
+    0b000000, 0b000000, 0b011100, 0b010010, 0b010010, 0b011100, 0b010000, 0b010000,
+];
+pub const SLIM_MILLIMETRE: [u8; 8] = [
+    0b000000, 0b000000, 0b000000, 0b011110, 0b010101, 0b010101, 0b010101, 0b010101,
+];
+pub const SLIM_MINUS: [u8; 8] = [0b0000, 0b0000, 0b0000, 0b0000, 0b0111, 0b0000, 0b0000, 0b0000];
 pub const SLIM_HUMIDITY: [u8; 8] = [
     0b0000000, 0b0000000, 0b0000000, 0b1000000, 0b1000000, 0b1110101, 0b1010101, 0b1010111,
 ];
 pub const SLIM_SEMICOLON: [u8; 8] = [0b00, 0b00, 0b01, 0b00, 0b00, 0b01, 0b00, 0b00];
 pub const SLIM_DOT: [u8; 8] = [0b00, 0b00, 0b00, 0b00, 0b00, 0b00, 0b00, 0b01];
+
+// tiny (3 pixels wide), used when more than four digits are required
+pub const TINY_NUMS: [[u8; 8]; 10] = [
+    TINY_N_0, TINY_N_1, TINY_N_2, TINY_N_3, TINY_N_4, TINY_N_5, TINY_N_6, TINY_N_7, TINY_N_8, TINY_N_9,
+];
+
+pub const TINY_N_0: [u8; 8] = [0b000, 0b010, 0b101, 0b101, 0b101, 0b101, 0b101, 0b010];
+pub const TINY_N_1: [u8; 8] = [0b000, 0b010, 0b110, 0b010, 0b010, 0b010, 0b010, 0b111];
+pub const TINY_N_2: [u8; 8] = [0b000, 0b010, 0b101, 0b001, 0b010, 0b100, 0b100, 0b111];
+pub const TINY_N_3: [u8; 8] = [0b000, 0b110, 0b001, 0b001, 0b010, 0b001, 0b001, 0b110];
+pub const TINY_N_4: [u8; 8] = [0b000, 0b101, 0b101, 0b101, 0b111, 0b001, 0b001, 0b001];
+pub const TINY_N_5: [u8; 8] = [0b000, 0b111, 0b100, 0b100, 0b110, 0b001, 0b001, 0b110];
+pub const TINY_N_6: [u8; 8] = [0b000, 0b011, 0b100, 0b100, 0b110, 0b101, 0b101, 0b010];
+pub const TINY_N_7: [u8; 8] = [0b000, 0b111, 0b001, 0b001, 0b010, 0b010, 0b100, 0b100];
+pub const TINY_N_8: [u8; 8] = [0b000, 0b010, 0b101, 0b101, 0b010, 0b101, 0b101, 0b010];
+pub const TINY_N_9: [u8; 8] = [0b000, 0b010, 0b101, 0b101, 0b011, 0b001, 0b001, 0b110];
+pub const TINY_SEMICOLON: [u8; 8] = [0b0, 0b0, 0b0, 0b1, 0b0, 0b0, 0b1, 0b0];
+pub const TINY_MINUS: [u8; 8] = [0b00, 0b00, 0b00, 0b00, 0b11, 0b00, 0b00, 0b00];
+pub const TINY_DOT: [u8; 8] = [0b0, 0b0, 0b0, 0b0, 0b0, 0b0, 0b0, 0b1];
+/// CO2 traffic light, the lit lamp is at the bottom for the fresh air and at the top for the stale one
+pub const CO2_GOOD: [u8; 8] = [0b010, 0b000, 0b000, 0b010, 0b000, 0b000, 0b111, 0b111];
+pub const CO2_FAIR: [u8; 8] = [0b010, 0b000, 0b000, 0b111, 0b111, 0b000, 0b010, 0b000];
+pub const CO2_POOR: [u8; 8] = [0b111, 0b111, 0b000, 0b010, 0b000, 0b000, 0b010, 0b000];
+/// droplet of the dew point
+pub const DEW_POINT: [u8; 8] = [0b000, 0b000, 0b000, 0b010, 0b010, 0b111, 0b111, 0b010];
+/// thermometer of the heat index
+pub const HEAT_INDEX: [u8; 8] = [0b010, 0b010, 0b010, 0b010, 0b010, 0b111, 0b111, 0b111];
+pub const HUMIDEX: [u8; 8] = [0b000, 0b000, 0b101, 0b101, 0b111, 0b101, 0b101, 0b101];
+/// grams per cubic metre of the absolute humidity, shared by both styles
+pub const GRAM: [u8; 8] = [
+    0b000000, 0b000000, 0b011111, 0b110011, 0b110011, 0b011111, 0b000011, 0b011110,
+];
+/// an empty droplet for the dry air, a smile for the comfortable one, a full droplet for the humid one
+/// and a droplet with an exclamation mark when mould might grow
+pub const COMFORT_DRY: [u8; 8] = [
+    0b0001000, 0b0010100, 0b0100010, 0b1000001, 0b1000001, 0b1000001, 0b0100010, 0b0011100,
+];
+pub const COMFORT_GOOD: [u8; 8] = [
+    0b0111110, 0b1000001, 0b1010101, 0b1000001, 0b1100011, 0b1011101, 0b0111110, 0b0000000,
+];
+pub const COMFORT_HUMID: [u8; 8] = [
+    0b0001000, 0b0011100, 0b0111110, 0b1111111, 0b1111111, 0b1111111, 0b0111110, 0b0011100,
+];
+pub const COMFORT_MOULD: [u8; 8] = [
+    0b0001000, 0b0011100, 0b0110110, 0b1110111, 0b1110111, 0b1111111, 0b0110110, 0b0011100,
+];
+/// arrows of the trend, the steady one is an equals sign
+pub const TREND_RISING: [u8; 8] = [0b000, 0b000, 0b010, 0b111, 0b010, 0b010, 0b010, 0b000];
+pub const TREND_FALLING: [u8; 8] = [0b000, 0b000, 0b010, 0b010, 0b010, 0b111, 0b010, 0b000];
+pub const TREND_STEADY: [u8; 8] = [0b000, 0b000, 0b000, 0b111, 0b000, 0b111, 0b000, 0b000];
+/// separates values shown side by side
+pub const BAR: [u8; 8] = [0b000, 0b010, 0b010, 0b010, 0b010, 0b010, 0b010, 0b010];
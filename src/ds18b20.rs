@@ -0,0 +1,276 @@
+use snafu::ResultExt;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{self, Error},
+    model,
+};
+
+/// Family code of DS18B20 in the one-wire device names, e.g. `28-0316a2794cff`
+const FAMILY: &str = "28-";
+/// Value of the scratchpad after power-on, the conversion has not been done
+const POWER_ON_MILLIDEGREES: i32 = 85000;
+
+/// DS18B20 temperature probe on the kernel `w1-gpio` bus
+pub(crate) struct Ds18b20 {
+    pub name: String,
+    path: PathBuf,
+}
+
+impl Ds18b20 {
+    /// Opens the configured probes, the ones without an id get the discovered probes in order of their ids
+    pub fn open_all(config: &model::Weather) -> Result<Vec<Self>, Error> {
+        if config.probes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let dir = Path::new(&config.w1_devices);
+        let mut discovered = discover(dir)?
+            .into_iter()
+            .filter(|id| config.probes.iter().all(|probe| probe.id.as_ref() != Some(id)));
+
+        config
+            .probes
+            .iter()
+            .map(|probe| {
+                let id = probe
+                    .id
+                    .clone()
+                    .or_else(|| discovered.next())
+                    .ok_or_else(|| Error::ProbeMissingError { name: probe.name.clone() })?;
+                let path = dir.join(&id);
+                if !path.is_dir() {
+                    return Err(Error::ProbeMissingError { name: probe.name.clone() });
+                }
+                Ok(Ds18b20 { name: probe.name.clone(), path })
+            })
+            .collect()
+    }
+
+    /// Temperature in celsius
+    pub fn read(&self) -> Result<f32, Error> {
+        let millidegrees = match fs::read_to_string(self.path.join("w1_slave")) {
+            Ok(text) => parse_w1_slave(&text).ok_or_else(|| self.data_error())??,
+            // newer kernels provide the checked value only
+            Err(_) => fs::read_to_string(self.path.join("temperature"))
+                .context(error::ProbeSnafu { name: self.name.clone() })?
+                .trim()
+                .parse()
+                .map_err(|_| self.data_error())?,
+        };
+        if millidegrees == POWER_ON_MILLIDEGREES {
+            return Err(self.data_error());
+        }
+        Ok(millidegrees as f32 / 1000.0)
+    }
+
+    fn data_error(&self) -> Error {
+        Error::ProbeDataError { name: self.name.clone() }
+    }
+}
+
+/// Ids of the probes found in the one-wire devices directory, sorted
+pub(crate) fn discover(dir: &Path) -> Result<Vec<String>, Error> {
+    let mut ids = fs::read_dir(dir)
+        .context(error::ProbeSnafu { name: dir.display().to_string() })?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.starts_with(FAMILY))
+        .collect::<Vec<_>>();
+    ids.sort();
+    Ok(ids)
+}
+
+/// Parses the scratchpad dump of the kernel driver:
+/// ```text
+/// 72 01 4b 46 7f ff 0e 10 57 : crc=57 YES
+/// 72 01 4b 46 7f ff 0e 10 57 t=23125
+/// ```
+/// `None` when the format is wrong, the CRC error when the scratchpad is corrupted.
+fn parse_w1_slave(text: &str) -> Option<Result<i32, Error>> {
+    let mut lines = text.lines();
+    let (scratchpad, status) = lines.next()?.split_once(':')?;
+    let bytes = scratchpad
+        .split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    if bytes.len() != 9 {
+        return None;
+    }
+    if crc8(&bytes[..8]) != bytes[8] || !status.trim_end().ends_with("YES") {
+        return Some(Err(Error::SensorCrcError {}));
+    }
+    let (_, millidegrees) = lines.next()?.rsplit_once("t=")?;
+    millidegrees.trim().parse().ok().map(Ok)
+}
+
+/// Dallas/Maxim CRC-8 (reflected 0x31 polynomial), 0x57 for the scratchpad above
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x01 != 0 { (crc >> 1) ^ 0x8C } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Scratchpad of the example in the driver documentation, 23.125°C
+    const SCRATCHPAD: &str = "72 01 4b 46 7f ff 0e 10 57";
+
+    /// One-wire devices directory with the probes and the files they provide
+    fn devices(probes: &[(&str, &str, &str)]) -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("w1_bus_master1")).unwrap();
+        for (id, file, content) in probes {
+            fs::create_dir(dir.path().join(id)).unwrap();
+            fs::write(dir.path().join(id).join(file), content).unwrap();
+        }
+        dir
+    }
+
+    fn w1_slave(scratchpad: &str, status: &str, millidegrees: i32) -> String {
+        format!(
+            "{0} : crc={1} {2}\n{0} t={3}\n",
+            scratchpad,
+            scratchpad.rsplit(' ').next().unwrap(),
+            status,
+            millidegrees
+        )
+    }
+
+    fn config(dir: &TempDir, probes: &[(&str, Option<&str>)]) -> model::Weather {
+        let mut config = model::Config::new().weather;
+        config.w1_devices = dir.path().display().to_string();
+        config.probes = probes
+            .iter()
+            .map(|(name, id)| model::Probe {
+                name: name.to_string(),
+                id: id.map(str::to_string),
+                calibration: Default::default(),
+            })
+            .collect();
+        config
+    }
+
+    fn probe(dir: &TempDir, id: &str) -> Ds18b20 {
+        Ds18b20 {
+            name: "outside".to_string(),
+            path: dir.path().join(id),
+        }
+    }
+
+    #[test]
+    fn reads_checked_temperature() {
+        let dir = devices(&[
+            ("28-000000000001", "w1_slave", &w1_slave(SCRATCHPAD, "YES", 23125)),
+            ("28-000000000002", "temperature", "-1250\n"),
+        ]);
+        assert_eq!(probe(&dir, "28-000000000001").read().unwrap(), 23.125);
+        assert_eq!(probe(&dir, "28-000000000002").read().unwrap(), -1.25);
+    }
+
+    #[test]
+    fn rejects_crc_errors() {
+        let dir = devices(&[
+            ("28-000000000001", "w1_slave", &w1_slave(SCRATCHPAD, "NO", 23125)),
+            // a bit flipped on the way, the kernel still says YES
+            (
+                "28-000000000002",
+                "w1_slave",
+                &w1_slave("73 01 4b 46 7f ff 0e 10 57", "YES", 23187),
+            ),
+        ]);
+        assert!(matches!(
+            probe(&dir, "28-000000000001").read(),
+            Err(Error::SensorCrcError {})
+        ));
+        assert!(matches!(
+            probe(&dir, "28-000000000002").read(),
+            Err(Error::SensorCrcError {})
+        ));
+    }
+
+    #[test]
+    fn rejects_power_on_value() {
+        // 85°C is in the scratchpad until the first conversion
+        let mut bytes = [0x50, 0x05, 0x4b, 0x46, 0x7f, 0xff, 0x0c, 0x10, 0];
+        bytes[8] = crc8(&bytes[..8]);
+        let scratchpad = bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let dir = devices(&[
+            ("28-000000000001", "w1_slave", &w1_slave(&scratchpad, "YES", 85000)),
+            ("28-000000000002", "temperature", "85000\n"),
+            ("28-000000000003", "w1_slave", "garbage\n"),
+        ]);
+        for id in ["28-000000000001", "28-000000000002", "28-000000000003"] {
+            assert!(
+                matches!(probe(&dir, id).read(), Err(Error::ProbeDataError { .. })),
+                "{}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn finds_probes() {
+        let dir = devices(&[
+            ("28-000000000002", "temperature", "20000\n"),
+            ("28-000000000001", "temperature", "21000\n"),
+            ("28-000000000003", "temperature", "22000\n"),
+        ]);
+        assert_eq!(
+            discover(dir.path()).unwrap(),
+            ["28-000000000001", "28-000000000002", "28-000000000003"]
+        );
+
+        // the probes without ids get the free ones in order
+        let config = config(
+            &dir,
+            &[
+                ("outside", None),
+                ("greenhouse", Some("28-000000000001")),
+                ("cellar", None),
+            ],
+        );
+        let probes = Ds18b20::open_all(&config).unwrap();
+        let ids = probes
+            .iter()
+            .map(|probe| probe.path.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["28-000000000002", "28-000000000001", "28-000000000003"]);
+        assert_eq!(probes[1].read().unwrap(), 21.0);
+    }
+
+    #[test]
+    fn reports_missing_probes() {
+        let dir = devices(&[("28-000000000001", "temperature", "20000\n")]);
+        let missing = |probes: &[(&str, Option<&str>)]| match Ds18b20::open_all(&config(&dir, probes)) {
+            Err(Error::ProbeMissingError { name }) => Some(name),
+            _ => None,
+        };
+        assert_eq!(
+            missing(&[("outside", Some("28-0000000000ff"))]).as_deref(),
+            Some("outside")
+        );
+        assert_eq!(
+            missing(&[("outside", None), ("cellar", None)]).as_deref(),
+            Some("cellar")
+        );
+
+        // unplugged after the start
+        let probe = probe(&dir, "28-000000000001");
+        fs::remove_dir_all(dir.path().join("28-000000000001")).unwrap();
+        assert!(matches!(probe.read(), Err(Error::ProbeError { .. })));
+    }
+}
@@ -0,0 +1,20 @@
+use snafu::Snafu;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum Error<I2CError>
+where
+    I2CError: 'static + std::error::Error,
+{
+    #[snafu(display("DS3231 read error"))]
+    ReadError { source: I2CError },
+
+    #[snafu(display("DS3231 write error"))]
+    WriteError { source: I2CError },
+
+    #[snafu(display("DS3231 holds invalid time"))]
+    InvalidTimeError {},
+
+    #[snafu(display("DS3231 supports years 2000-2199 only, got {}", year))]
+    YearRangeError { year: i32 },
+}
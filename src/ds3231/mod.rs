@@ -0,0 +1,410 @@
+pub mod error;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike};
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+use error::Error;
+use snafu::ResultExt;
+
+const I2C_ADDRESS: u8 = 0x68; // DS3231 has it's own static address
+
+const REG_TIME: u8 = 0x00;
+const REG_ALARM1: u8 = 0x07;
+const REG_ALARM2: u8 = 0x0B;
+const REG_CONTROL: u8 = 0x0E;
+const REG_STATUS: u8 = 0x0F;
+const REG_AGING: u8 = 0x10;
+const REG_TEMPERATURE: u8 = 0x11;
+
+const HOURS_12: u8 = 1 << 6; // 1 - 12 hours mode
+const HOURS_PM: u8 = 1 << 5; // in 12 hours mode
+const CENTURY: u8 = 1 << 7; // in month register, year is 21xx
+const ALARM_MASK: u8 = 1 << 7; // field does not take part in matching
+const ALARM_WEEKDAY: u8 = 1 << 6; // day field is a day of week, not a date
+
+bitflags! {
+    struct ControlFlags: u8 {
+        const INTERRUPTS = (1 << 2); // 1 - INT/SQW pin is driven by alarms, 0 - square wave
+        const ALARM2 = (1 << 1); // alarm 2 interrupt enabled
+        const ALARM1 = (1 << 0); // alarm 1 interrupt enabled
+    }
+}
+
+bitflags! {
+    struct StatusFlags: u8 {
+        const OSCILLATOR_STOPPED = (1 << 7); // 1 - time is not valid (power was lost)
+        const ALARM2 = (1 << 1); // alarm 2 fired
+        const ALARM1 = (1 << 0); // alarm 1 fired
+    }
+}
+
+/// Which part of the alarm time has to match the current time to fire it.
+/// Alarm 2 has no seconds, `Second` means the same as `Every` (once a minute) for it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlarmMatch {
+    /// every second for alarm 1, every minute for alarm 2
+    Every,
+    Second,
+    Minute,
+    Hour,
+    Date,
+    Weekday,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alarm {
+    pub matching: AlarmMatch,
+    pub second: u8,
+    pub minute: u8,
+    pub hour: u8,
+    /// day of month for `AlarmMatch::Date`, day of week (1 - Monday) for `AlarmMatch::Weekday`
+    pub day: u8,
+}
+
+/// DS3231 real-time clock. The time is kept in UTC, 24 hours mode.
+pub struct DS3231<I2C> {
+    pub(crate) i2c: I2C,
+}
+
+impl<I2C, I2CError> DS3231<I2C>
+where
+    I2CError: std::error::Error,
+    I2C: Write<Error = I2CError> + WriteRead<Error = I2CError>,
+{
+    pub fn datetime(&mut self) -> Result<NaiveDateTime, Error<I2CError>> {
+        let mut buf = [0; 7];
+        self.read(REG_TIME, &mut buf)?;
+        decode_datetime(&buf).ok_or(Error::InvalidTimeError {})
+    }
+
+    /// Sets the time and clears the "oscillator stopped" flag
+    pub fn set_datetime(&mut self, time: &NaiveDateTime) -> Result<(), Error<I2CError>> {
+        self.write(REG_TIME, &encode_datetime(time)?)?;
+
+        let status = self.status()? - StatusFlags::OSCILLATOR_STOPPED;
+        self.write(REG_STATUS, &[status.bits])
+    }
+
+    /// `true` when the oscillator has been stopped (e.g. no battery) and the time is not valid
+    pub fn lost_power(&mut self) -> Result<bool, Error<I2CError>> {
+        Ok(self.status()?.contains(StatusFlags::OSCILLATOR_STOPPED))
+    }
+
+    /// Temperature of the chip in celsius, 0.25 resolution, updated every 64 seconds
+    pub fn temperature(&mut self) -> Result<f32, Error<I2CError>> {
+        let mut buf = [0; 2];
+        self.read(REG_TEMPERATURE, &mut buf)?;
+        Ok(decode_temperature(buf))
+    }
+
+    /// Crystal aging offset, a positive value slows the clock down (about 0.1 ppm per step)
+    pub fn aging_offset(&mut self) -> Result<i8, Error<I2CError>> {
+        let mut buf = [0; 1];
+        self.read(REG_AGING, &mut buf)?;
+        Ok(buf[0] as i8)
+    }
+
+    pub fn set_aging_offset(&mut self, offset: i8) -> Result<(), Error<I2CError>> {
+        self.write(REG_AGING, &[offset as u8])
+    }
+
+    pub fn alarm1(&mut self) -> Result<Alarm, Error<I2CError>> {
+        let mut buf = [0; 4];
+        self.read(REG_ALARM1, &mut buf)?;
+        Ok(decode_alarm(buf))
+    }
+
+    pub fn set_alarm1(&mut self, alarm: &Alarm) -> Result<(), Error<I2CError>> {
+        self.write(REG_ALARM1, &encode_alarm(alarm))
+    }
+
+    pub fn alarm2(&mut self) -> Result<Alarm, Error<I2CError>> {
+        let mut buf = [0; 3];
+        self.read(REG_ALARM2, &mut buf)?;
+        // alarm 2 has no seconds, they match as soon as minutes do
+        Ok(decode_alarm([buf[0] & ALARM_MASK, buf[0], buf[1], buf[2]]))
+    }
+
+    pub fn set_alarm2(&mut self, alarm: &Alarm) -> Result<(), Error<I2CError>> {
+        self.write(REG_ALARM2, &encode_alarm(alarm)[1..])
+    }
+
+    /// Routes alarms to the INT/SQW pin, `None` keeps the alarm enabled or disabled as it is
+    pub fn enable_alarms(&mut self, alarm1: Option<bool>, alarm2: Option<bool>) -> Result<(), Error<I2CError>> {
+        let mut buf = [0; 1];
+        self.read(REG_CONTROL, &mut buf)?;
+        let mut control = ControlFlags::from_bits_truncate(buf[0]) | ControlFlags::INTERRUPTS;
+        if let Some(alarm1) = alarm1 {
+            control.set(ControlFlags::ALARM1, alarm1);
+        }
+        if let Some(alarm2) = alarm2 {
+            control.set(ControlFlags::ALARM2, alarm2);
+        }
+        // keep the rest of the register (square wave rate, oscillator and conversion bits) untouched
+        let control = (buf[0] & !ControlFlags::all().bits) | control.bits;
+        self.write(REG_CONTROL, &[control])
+    }
+
+    /// Returns which alarms have fired and clears their flags
+    pub fn take_alarms(&mut self) -> Result<(bool, bool), Error<I2CError>> {
+        let status = self.status()?;
+        let fired = (
+            status.contains(StatusFlags::ALARM1),
+            status.contains(StatusFlags::ALARM2),
+        );
+        if fired.0 || fired.1 {
+            let status = status - StatusFlags::ALARM1 - StatusFlags::ALARM2;
+            self.write(REG_STATUS, &[status.bits])?;
+        }
+        Ok(fired)
+    }
+
+    fn status(&mut self) -> Result<StatusFlags, Error<I2CError>> {
+        let mut buf = [0; 1];
+        self.read(REG_STATUS, &mut buf)?;
+        // the rest bits are kept as is when the flags are written back
+        Ok(StatusFlags { bits: buf[0] })
+    }
+
+    fn read(&mut self, register: u8, buf: &mut [u8]) -> Result<(), Error<I2CError>> {
+        self.i2c
+            .write_read(I2C_ADDRESS, &[register], buf)
+            .context(error::ReadSnafu)
+    }
+
+    fn write(&mut self, register: u8, data: &[u8]) -> Result<(), Error<I2CError>> {
+        let mut buf = Vec::with_capacity(data.len() + 1);
+        buf.push(register);
+        buf.extend_from_slice(data);
+        self.i2c.write(I2C_ADDRESS, &buf).context(error::WriteSnafu)
+    }
+}
+
+fn bcd(value: u32) -> u8 {
+    (((value / 10 % 10) << 4) | (value % 10)) as u8
+}
+
+fn from_bcd(value: u8) -> u32 {
+    ((value >> 4) as u32) * 10 + (value & 0x0f) as u32
+}
+
+fn decode_hours(value: u8) -> u32 {
+    if value & HOURS_12 == 0 {
+        return from_bcd(value & 0x3f);
+    }
+    let hours = from_bcd(value & 0x1f) % 12;
+    if value & HOURS_PM != 0 {
+        hours + 12
+    } else {
+        hours
+    }
+}
+
+fn encode_datetime<E>(time: &NaiveDateTime) -> Result<[u8; 7], Error<E>>
+where
+    E: 'static + std::error::Error,
+{
+    let year = time.year();
+    if !(2000..2200).contains(&year) {
+        return Err(Error::YearRangeError { year });
+    }
+    let century = if year >= 2100 { CENTURY } else { 0 };
+
+    Ok([
+        bcd(time.second()),
+        bcd(time.minute()),
+        bcd(time.hour()),
+        bcd(time.weekday().number_from_monday()),
+        bcd(time.day()),
+        bcd(time.month()) | century,
+        bcd(year as u32 % 100),
+    ])
+}
+
+fn decode_datetime(buf: &[u8; 7]) -> Option<NaiveDateTime> {
+    let century = if buf[5] & CENTURY != 0 { 2100 } else { 2000 };
+    let year = century + from_bcd(buf[6]) as i32;
+    NaiveDate::from_ymd_opt(year, from_bcd(buf[5] & 0x1f), from_bcd(buf[4] & 0x3f))?.and_hms_opt(
+        decode_hours(buf[2]),
+        from_bcd(buf[1] & 0x7f),
+        from_bcd(buf[0] & 0x7f),
+    )
+}
+
+fn decode_temperature(buf: [u8; 2]) -> f32 {
+    (buf[0] as i8) as f32 + (buf[1] >> 6) as f32 * 0.25
+}
+
+/// Encodes all 4 alarm fields: seconds, minutes, hours and day, alarm 2 uses the last three of them
+fn encode_alarm(alarm: &Alarm) -> [u8; 4] {
+    // number of fields taking part in matching, starting from seconds
+    let matched = match alarm.matching {
+        AlarmMatch::Every => 0,
+        AlarmMatch::Second => 1,
+        AlarmMatch::Minute => 2,
+        AlarmMatch::Hour => 3,
+        AlarmMatch::Date | AlarmMatch::Weekday => 4,
+    };
+    let day = match alarm.matching {
+        AlarmMatch::Weekday => bcd(alarm.day as u32) | ALARM_WEEKDAY,
+        _ => bcd(alarm.day as u32),
+    };
+
+    let mut fields = [
+        bcd(alarm.second as u32),
+        bcd(alarm.minute as u32),
+        bcd(alarm.hour as u32),
+        day,
+    ];
+    for field in fields.iter_mut().skip(matched) {
+        *field |= ALARM_MASK;
+    }
+    fields
+}
+
+fn decode_alarm(fields: [u8; 4]) -> Alarm {
+    let matching = match fields.iter().take_while(|f| *f & ALARM_MASK == 0).count() {
+        0 => AlarmMatch::Every,
+        1 => AlarmMatch::Second,
+        2 => AlarmMatch::Minute,
+        3 => AlarmMatch::Hour,
+        _ if fields[3] & ALARM_WEEKDAY != 0 => AlarmMatch::Weekday,
+        _ => AlarmMatch::Date,
+    };
+    Alarm {
+        matching,
+        second: from_bcd(fields[0] & 0x7f) as u8,
+        minute: from_bcd(fields[1] & 0x7f) as u8,
+        hour: decode_hours(fields[2] & 0x7f) as u8,
+        day: from_bcd(fields[3] & 0x3f) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    /// Register file of the chip behind the I2C bus
+    struct Registers([u8; 0x13]);
+
+    impl WriteRead for Registers {
+        type Error = Infallible;
+
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Infallible> {
+            assert_eq!(address, I2C_ADDRESS);
+            let start = bytes[0] as usize;
+            buffer.copy_from_slice(&self.0[start..start + buffer.len()]);
+            Ok(())
+        }
+    }
+
+    impl Write for Registers {
+        type Error = Infallible;
+
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Infallible> {
+            assert_eq!(address, I2C_ADDRESS);
+            let start = bytes[0] as usize;
+            self.0[start..start + bytes.len() - 1].copy_from_slice(&bytes[1..]);
+            Ok(())
+        }
+    }
+
+    fn rtc(set: &[(u8, u8)]) -> DS3231<Registers> {
+        let mut registers = [0; 0x13];
+        for (register, value) in set {
+            registers[*register as usize] = *value;
+        }
+        DS3231 { i2c: Registers(registers) }
+    }
+
+    fn time(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, mo, d).and_hms(h, mi, s)
+    }
+
+    #[test]
+    fn sets_time_in_bcd_and_clears_oscillator_flag() {
+        let mut rtc = rtc(&[(REG_STATUS, 0x83)]);
+        rtc.set_datetime(&time(2021, 12, 31, 23, 59, 58)).unwrap();
+        // Friday is the 5th day
+        assert_eq!(rtc.i2c.0[..7], [0x58, 0x59, 0x23, 0x05, 0x31, 0x12, 0x21]);
+        assert_eq!(rtc.i2c.0[REG_STATUS as usize], 0x03);
+        assert!(!rtc.lost_power().unwrap());
+        assert_eq!(rtc.datetime().unwrap(), time(2021, 12, 31, 23, 59, 58));
+    }
+
+    #[test]
+    fn keeps_century_in_month() {
+        let mut rtc = rtc(&[]);
+        rtc.set_datetime(&time(2105, 3, 1, 0, 0, 0)).unwrap();
+        assert_eq!(rtc.i2c.0[5], 0x83);
+        assert_eq!(rtc.i2c.0[6], 0x05);
+        assert_eq!(rtc.datetime().unwrap(), time(2105, 3, 1, 0, 0, 0));
+        assert!(matches!(
+            rtc.set_datetime(&time(1999, 1, 1, 0, 0, 0)),
+            Err(Error::YearRangeError { year: 1999 })
+        ));
+    }
+
+    #[test]
+    fn reads_12_hours_mode() {
+        let registers = |hours| [(1, 0x30), (2, hours), (4, 0x01), (5, 0x01), (6, 0x22)];
+        assert_eq!(rtc(&registers(0x71)).datetime().unwrap(), time(2022, 1, 1, 23, 30, 0));
+        assert_eq!(rtc(&registers(0x52)).datetime().unwrap(), time(2022, 1, 1, 0, 30, 0));
+        assert_eq!(rtc(&registers(0x72)).datetime().unwrap(), time(2022, 1, 1, 12, 30, 0));
+        assert!(rtc(&[(4, 0x32), (5, 0x02)]).datetime().is_err());
+    }
+
+    #[test]
+    fn reads_temperature_and_aging() {
+        let mut rtc = rtc(&[(REG_TEMPERATURE, 0x19), (REG_TEMPERATURE + 1, 0x40), (REG_AGING, 0xFE)]);
+        assert_eq!(rtc.temperature().unwrap(), 25.25);
+        assert_eq!(rtc.aging_offset().unwrap(), -2);
+        // 0b11100111_11 is -24.25 in the datasheet
+        let mut rtc = self::rtc(&[(REG_TEMPERATURE, 0xE7), (REG_TEMPERATURE + 1, 0xC0)]);
+        assert_eq!(rtc.temperature().unwrap(), -24.25);
+    }
+
+    #[test]
+    fn encodes_alarms() {
+        let mut rtc = rtc(&[]);
+        let daily = Alarm {
+            matching: AlarmMatch::Hour,
+            second: 15,
+            minute: 30,
+            hour: 7,
+            day: 1,
+        };
+        rtc.set_alarm1(&daily).unwrap();
+        assert_eq!(rtc.i2c.0[7..11], [0x15, 0x30, 0x07, 0x81]);
+        assert_eq!(rtc.alarm1().unwrap(), daily);
+
+        rtc.set_alarm2(&daily).unwrap();
+        assert_eq!(rtc.i2c.0[11..14], [0x30, 0x07, 0x81]);
+        assert_eq!(rtc.alarm2().unwrap(), Alarm { second: 0, ..daily });
+
+        let weekly = Alarm { matching: AlarmMatch::Weekday, day: 6, ..daily };
+        rtc.set_alarm1(&weekly).unwrap();
+        assert_eq!(rtc.i2c.0[7..11], [0x15, 0x30, 0x07, 0x46]);
+        assert_eq!(rtc.alarm1().unwrap(), weekly);
+    }
+
+    #[test]
+    fn enables_only_given_alarms() {
+        // square wave rate bits and alarm 2 are set already
+        let mut rtc = rtc(&[(REG_CONTROL, 0x1A)]);
+        rtc.enable_alarms(Some(true), None).unwrap();
+        assert_eq!(rtc.i2c.0[REG_CONTROL as usize], 0x1F);
+        rtc.enable_alarms(None, Some(false)).unwrap();
+        assert_eq!(rtc.i2c.0[REG_CONTROL as usize], 0x1D);
+        rtc.enable_alarms(Some(false), Some(false)).unwrap();
+        assert_eq!(rtc.i2c.0[REG_CONTROL as usize], 0x1C);
+    }
+
+    #[test]
+    fn takes_fired_alarms() {
+        let mut rtc = rtc(&[(REG_STATUS, 0x8A)]);
+        assert_eq!(rtc.take_alarms().unwrap(), (false, true));
+        assert_eq!(rtc.i2c.0[REG_STATUS as usize], 0x88);
+        assert_eq!(rtc.take_alarms().unwrap(), (false, false));
+    }
+}
@@ -1,7 +1,7 @@
 use clock_macro::SnafuDebug;
 use snafu::Snafu;
 
-use crate::aht10;
+use crate::{aht10, bme280, ds3231, mhz19, scd4x, sht};
 
 #[derive(Snafu, SnafuDebug)]
 #[snafu(visibility(pub))]
@@ -18,12 +18,103 @@ pub enum Error {
     #[snafu(display("MAX7219 data error."))]
     Max7219DataError { source: DataError },
 
+    #[snafu(display("Unknown clock face `{}`.", name))]
+    FaceError { name: String },
+
+    #[snafu(display(
+        "Wrong profile start `{}`, expected HH:MM or dawn/sunrise/sunset/dusk with optional +HH:MM or -HH:MM.",
+        from
+    ))]
+    ProfileTimeError { from: String },
+    #[snafu(display("Location is required for sun events."))]
+    LocationError {},
+    #[snafu(display("Wrong event date `{}`, expected YYYY-MM-DD or MM-DD with optional HH:MM.", date))]
+    EventDateError { date: String },
+    #[snafu(display("Agenda screen is configured without the calendar."))]
+    AgendaConfigError {},
+    #[snafu(display("Screen `{}` needs a sensor measuring humidity.", screen))]
+    ScreenHumidityError { screen: String },
+
     #[snafu(display("I2C connection error."))]
     I2CError { source: i2cdev::linux::LinuxI2CError },
+    #[snafu(display("Unknown sensor driver `{}`.", name))]
+    SensorDriverError { name: String },
+    #[snafu(display("Wrong sensor {} `{}`.", option, value))]
+    SensorOptionError { option: String, value: u8 },
+    #[snafu(display("SHT communication error."))]
+    ShtError {
+        source: sht::error::Error<i2cdev::linux::LinuxI2CError>,
+    },
+    #[snafu(display("Sensor data CRC mismatch."))]
+    SensorCrcError {},
+    #[snafu(display("Sensor measurement timeout."))]
+    SensorTimeoutError {},
+    #[snafu(display("Sensor is cooling down after heating."))]
+    SensorCoolingError {},
+    #[snafu(display("Cannot read one-wire probe `{}`.", name))]
+    ProbeError { name: String, source: std::io::Error },
+    #[snafu(display("Wrong data of one-wire probe `{}`.", name))]
+    ProbeDataError { name: String },
+    #[snafu(display("One-wire probe `{}` is not found.", name))]
+    ProbeMissingError { name: String },
+    #[snafu(display("SCD4x communication error."))]
+    Scd4xError {
+        source: scd4x::error::Error<i2cdev::linux::LinuxI2CError>,
+    },
+    #[snafu(display("MH-Z19 communication error."))]
+    Mhz19Error { source: mhz19::error::Error },
+    #[snafu(display("Cannot read CPU temperature from `{}`.", path))]
+    ThermalZoneError { path: String, source: std::io::Error },
+    #[snafu(display("Wrong CPU temperature in `{}`.", path))]
+    ThermalZoneDataError { path: String },
+    #[snafu(display("Cannot read the temperature log."))]
+    CompensationLogError { source: std::io::Error },
+    #[snafu(display("Column `{}` is missing in the temperature log.", name))]
+    CompensationColumnError { name: String },
+    #[snafu(display("Cannot fit the compensation to {} samples, the heat load has to vary.", samples))]
+    CompensationFitError { samples: usize },
+    #[snafu(display("Cannot write the log `{}`.", path))]
+    LogError { path: String, source: std::io::Error },
+    #[snafu(display("Cannot read the logs in `{}`.", path))]
+    LogReadError { path: String, source: std::io::Error },
+    #[snafu(display("Log is not configured."))]
+    LogConfigError {},
+    #[snafu(display("Unknown log format `{}`.", format))]
+    LogFormatError { format: String },
+    #[snafu(display("BME280 communication error."))]
+    Bme280Error {
+        source: bme280::error::Error<i2cdev::linux::LinuxI2CError>,
+    },
     #[snafu(display("I2C communication error."))]
     SensorError {
         source: aht10::error::Error<i2cdev::linux::LinuxI2CError>,
     },
+
+    #[snafu(display("RTC communication error."))]
+    RtcError {
+        source: ds3231::error::Error<i2cdev::linux::LinuxI2CError>,
+    },
+    #[snafu(display("RTC is not configured."))]
+    RtcConfigError {},
+    #[snafu(display("Wrong RTC option `{}`.", value))]
+    RtcOptionError { value: String },
+    #[snafu(display("Cannot set system time."))]
+    SystemTimeError { source: std::io::Error },
+
+    #[snafu(display("PWM error."))]
+    PwmError { source: sysfs_pwm::Error },
+
+    #[snafu(display("Control socket error."))]
+    ControlError { source: std::io::Error },
+    #[snafu(display("Button GPIO error."))]
+    ButtonError { source: gpio_cdev::Error },
+    #[snafu(display("Unknown command `{}`.", command))]
+    CommandError { command: String },
+    #[snafu(display("Unknown timer preset or duration `{}`, durations are up to 99:59:59.", duration))]
+    TimerDurationError { duration: String },
+
+    #[snafu(display("Wrong time option `{}`.", value))]
+    TimeOptionError { value: String },
 }
 
 // WRAPPERS for max7219::DataError
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Local, Timelike};
+
+use super::Face;
+use crate::{display::LinearMatrixDisplay, error::Error};
+
+/// Bar chart: part of the day passed on the top, of the hour in the middle and of the minute at the bottom.
+/// Quarters of the hour are marked between the top and middle bars.
+pub(super) struct Bar;
+
+impl Face for Bar {
+    fn draw(
+        &mut self,
+        display: &mut LinearMatrixDisplay,
+        time: &DateTime<Local>,
+        _draw_dots: bool,
+    ) -> Result<(), Error> {
+        let width = display.width() as u32;
+        let day = width * (time.hour() * 60 + time.minute()) / (24 * 60);
+        let hour = width * (time.minute() * 60 + time.second()) / 3600;
+        let minute = width * time.second() / 60;
+
+        display.draw(|x, y| {
+            let x = x as u32;
+            let lit = match y {
+                0..=2 => x < day,
+                3 => x.is_multiple_of(width / 4),
+                4 | 5 => x < hour,
+                7 => x < minute,
+                _ => false,
+            };
+            lit as u8
+        })
+    }
+}
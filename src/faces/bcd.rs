@@ -0,0 +1,41 @@
+use chrono::{DateTime, Local, Timelike};
+
+use super::Face;
+use crate::{display::LinearMatrixDisplay, error::Error};
+
+/// Largest value of each BCD column: tens and units of hours, minutes and seconds
+const MAX_DIGITS: [u32; 6] = [2, 9, 5, 9, 5, 9];
+
+/// Six columns of binary coded decimal digits, the lowest bit at the bottom.
+/// A set bit is a 3 pixels line, a cleared one is a single dot, bits which are never set are not shown.
+pub(super) struct Bcd;
+
+impl Face for Bcd {
+    fn draw(
+        &mut self,
+        display: &mut LinearMatrixDisplay,
+        time: &DateTime<Local>,
+        _draw_dots: bool,
+    ) -> Result<(), Error> {
+        let (hour, minute, second) = (time.hour(), time.minute(), time.second());
+        let digits = [hour / 10, hour % 10, minute / 10, minute % 10, second / 10, second % 10];
+
+        display.draw(|x, y| {
+            if x < 2 || y % 2 == 0 {
+                return 0;
+            }
+            // pairs of 3 pixels wide columns with 1 pixel between them and 3 pixels between pairs
+            let (pair, offset) = ((x - 2) / 10, (x - 2) % 10);
+            if pair > 2 || offset == 3 || offset >= 7 {
+                return 0;
+            }
+            let column = pair * 2 + offset / 4;
+            let bit = (7 - y) / 2;
+            if digits[column] & (1 << bit) != 0 {
+                1
+            } else {
+                (MAX_DIGITS[column] >= (1 << bit) && offset % 4 == 1) as u8
+            }
+        })
+    }
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Local, Timelike};
+
+use super::Face;
+use crate::{display::LinearMatrixDisplay, error::Error};
+
+/// Hours, minutes and seconds as three rows of 6-bit binary numbers, most significant bit on the left.
+/// A set bit is a 3x2 block, a cleared one is a single dot.
+pub(super) struct Binary;
+
+impl Face for Binary {
+    fn draw(
+        &mut self,
+        display: &mut LinearMatrixDisplay,
+        time: &DateTime<Local>,
+        _draw_dots: bool,
+    ) -> Result<(), Error> {
+        let (hour, minute, second) = (time.hour(), time.minute(), time.second());
+
+        display.draw(|x, y| {
+            let value = match y {
+                0 | 1 => hour,
+                3 | 4 => minute,
+                6 | 7 => second,
+                _ => return 0,
+            };
+            if !(4..28).contains(&x) || (x - 4) % 4 == 3 {
+                return 0;
+            }
+            let bit = 5 - (x - 4) / 4;
+            if value & (1 << bit) != 0 {
+                1
+            } else {
+                // the lower row of the block, middle column
+                (y % 3 == 1 && (x - 4) % 4 == 1) as u8
+            }
+        })
+    }
+}
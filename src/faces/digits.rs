@@ -0,0 +1,20 @@
+use chrono::{DateTime, Local};
+
+use super::Face;
+use crate::{clock, display::LinearMatrixDisplay, error::Error};
+
+/// Big `HH:MM` digits, the original face
+pub(super) struct Digits {
+    pub slim: bool,
+}
+
+impl Face for Digits {
+    fn draw(
+        &mut self,
+        display: &mut LinearMatrixDisplay,
+        time: &DateTime<Local>,
+        draw_dots: bool,
+    ) -> Result<(), Error> {
+        clock::draw(display, time, draw_dots, self.slim)
+    }
+}
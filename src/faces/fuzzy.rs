@@ -0,0 +1,81 @@
+use chrono::{DateTime, Local, Timelike};
+
+use super::Face;
+use crate::{display::LinearMatrixDisplay, error::Error};
+
+const HOURS: [&str; 12] = [
+    "twelve", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven",
+];
+
+/// Time in words rounded to five minutes ("quarter past ten"), scrolled continuously
+pub(super) struct Fuzzy;
+
+impl Face for Fuzzy {
+    fn draw(
+        &mut self,
+        display: &mut LinearMatrixDisplay,
+        _time: &DateTime<Local>,
+        _draw_dots: bool,
+    ) -> Result<(), Error> {
+        display.clear()
+    }
+
+    fn scroll_text(&mut self, time: &DateTime<Local>) -> Option<String> {
+        Some(phrase(time))
+    }
+}
+
+fn phrase(time: &DateTime<Local>) -> String {
+    // rounded to the nearest five minutes, the next hour is named from "twenty-five to"
+    let minutes = (time.minute() + 2) / 5 * 5;
+    let hour = HOURS[((time.hour() + (minutes > 30) as u32) % 12) as usize];
+    match minutes {
+        0 | 60 => format!("{} o'clock", hour),
+        5 => format!("five past {}", hour),
+        10 => format!("ten past {}", hour),
+        15 => format!("quarter past {}", hour),
+        20 => format!("twenty past {}", hour),
+        25 => format!("twenty-five past {}", hour),
+        30 => format!("half past {}", hour),
+        35 => format!("twenty-five to {}", hour),
+        40 => format!("twenty to {}", hour),
+        45 => format!("quarter to {}", hour),
+        50 => format!("ten to {}", hour),
+        _ => format!("five to {}", hour),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> String {
+        phrase(&Local.ymd(2022, 1, 15).and_hms(hour, minute, 0))
+    }
+
+    #[test]
+    fn rounds_to_five_minutes() {
+        assert_eq!(at(10, 13), "quarter past ten");
+        assert_eq!(at(10, 32), "half past ten");
+        assert_eq!(at(10, 33), "twenty-five to eleven");
+        assert_eq!(at(10, 52), "ten to eleven");
+    }
+
+    #[test]
+    fn rounds_up_to_next_hour() {
+        assert_eq!(at(10, 57), "five to eleven");
+        assert_eq!(at(10, 58), "eleven o'clock");
+        assert_eq!(at(11, 58), "twelve o'clock");
+        assert_eq!(at(23, 58), "twelve o'clock");
+    }
+
+    #[test]
+    fn names_twelve_for_noon_and_midnight() {
+        assert_eq!(at(0, 0), "twelve o'clock");
+        assert_eq!(at(0, 2), "twelve o'clock");
+        assert_eq!(at(12, 0), "twelve o'clock");
+        assert_eq!(at(12, 5), "five past twelve");
+        assert_eq!(at(13, 0), "one o'clock");
+    }
+}
@@ -0,0 +1,95 @@
+mod bar;
+mod bcd;
+mod binary;
+mod digits;
+mod fuzzy;
+
+use chrono::{DateTime, Duration, Local};
+
+use crate::{
+    display::LinearMatrixDisplay,
+    error::Error,
+    model::{self, ColonMode},
+};
+
+/// Clock face: a way to show the time of day on the display.
+/// To add a face implement the trait in its own module and register the name in `create`.
+pub(crate) trait Face {
+    /// Draws the time, `draw_dots` is `false` during the second half of a second for blinking elements
+    fn draw(&mut self, display: &mut LinearMatrixDisplay, time: &DateTime<Local>, draw_dots: bool)
+        -> Result<(), Error>;
+
+    /// Text which does not fit the display and has to be scrolled before the next frame is drawn
+    fn scroll_text(&mut self, _time: &DateTime<Local>) -> Option<String> {
+        None
+    }
+}
+
+/// Creates the face by its name used in the config
+pub(crate) fn create(name: &str, slim: bool) -> Result<Box<dyn Face>, Error> {
+    let face: Box<dyn Face> = match name {
+        "digits" => Box::new(digits::Digits { slim }),
+        "slim" => Box::new(digits::Digits { slim: true }),
+        "binary" => Box::new(binary::Binary),
+        "bcd" => Box::new(bcd::Bcd),
+        "fuzzy" => Box::new(fuzzy::Fuzzy),
+        "bar" => Box::new(bar::Bar),
+        _ => return Err(Error::FaceError { name: name.to_string() }),
+    };
+    Ok(face)
+}
+
+/// Faces from the config, switched in turn every `rotate_sec` seconds
+pub(crate) struct Faces {
+    faces: Vec<Box<dyn Face>>,
+    colon: Vec<Option<ColonMode>>,
+    rotate: Option<Duration>,
+    current: usize,
+    switched_at: Option<DateTime<Local>>,
+}
+
+impl Faces {
+    pub fn new(config: &model::Faces, slim: bool) -> Result<Self, Error> {
+        let mut names = config.rotation.clone();
+        if names.is_empty() {
+            names.push("digits".to_string());
+        }
+        let faces = names
+            .iter()
+            .map(|name| create(name, slim))
+            .collect::<Result<Vec<_>, _>>()?;
+        let colon = names.iter().map(|name| config.colon.get(name).copied()).collect();
+        let rotate = (config.rotate_sec > 0).then(|| Duration::seconds(config.rotate_sec as i64));
+
+        Ok(Faces {
+            faces,
+            colon,
+            rotate,
+            current: 0,
+            switched_at: None,
+        })
+    }
+
+    /// Switches to the next face when its turn comes
+    pub fn rotate(&mut self, time: &DateTime<Local>) {
+        let switched_at = self.switched_at.get_or_insert(*time);
+        if let Some(rotate) = self.rotate {
+            if *time < *switched_at {
+                // time went backwards, restart the period
+                *switched_at = *time;
+            } else if *time - *switched_at >= rotate {
+                self.current = (self.current + 1) % self.faces.len();
+                *switched_at = *time;
+            }
+        }
+    }
+
+    pub fn face(&mut self) -> &mut dyn Face {
+        self.faces[self.current].as_mut()
+    }
+
+    /// Colon mode configured for the current face
+    pub fn colon(&self) -> Option<ColonMode> {
+        self.colon[self.current]
+    }
+}
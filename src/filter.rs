@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use crate::{
+    compensation::{Compensation, HeatLoad},
+    model::{self, SmoothingMethod},
+    sensor::Measurement,
+};
+
+/// A jump is accepted as real when it repeats in this many measurements in a row
+const MAX_REJECTIONS: usize = 3;
+
+/// Calibrates and smooths measurements of a sensor, rejects the ones jumping too far from the previous
+pub(crate) struct Filter {
+    compensation: Option<Compensation>,
+    /// heat load of the latest measurement
+    load: Option<HeatLoad>,
+    /// the heat load has failed to be measured, it is reported once
+    load_failed: bool,
+    calibration: model::Calibration,
+    smoothing: model::Smoothing,
+    /// latest values of temperature, humidity, pressure and CO2
+    windows: [VecDeque<f32>; 4],
+    last: Option<Measurement>,
+    rejected: usize,
+}
+
+impl Filter {
+    pub fn new(
+        calibration: &model::Calibration,
+        smoothing: &model::Smoothing,
+        compensation: Option<Compensation>,
+    ) -> Self {
+        Filter {
+            compensation,
+            load: None,
+            load_failed: false,
+            calibration: *calibration,
+            smoothing: *smoothing,
+            windows: Default::default(),
+            last: None,
+            rejected: 0,
+        }
+    }
+
+    /// Heat load the latest measurement is compensated for
+    pub fn load(&self) -> Option<HeatLoad> {
+        self.load
+    }
+
+    /// Compensated, calibrated and smoothed measurement, `None` when it is rejected
+    pub fn apply(&mut self, raw: &Measurement) -> Option<Measurement> {
+        let compensated = self.compensate(raw);
+        let corrected = self.calibrate(&compensated);
+        if let Some(last) = &self.last {
+            let jump = |limit: f32, value: Option<f32>, last: Option<f32>| match (value, last) {
+                (Some(value), Some(last)) => (value - last).abs() > limit,
+                _ => false,
+            };
+            let jumped = jump(
+                self.smoothing.max_temperature_jump,
+                Some(corrected.temperature),
+                Some(last.temperature),
+            ) || jump(self.smoothing.max_humidity_jump, corrected.humidity, last.humidity);
+            if jumped && self.rejected + 1 < MAX_REJECTIONS {
+                self.rejected += 1;
+                return None;
+            }
+            if jumped {
+                // the old values would drag the new level down
+                self.windows.iter_mut().for_each(VecDeque::clear);
+            }
+        }
+        self.rejected = 0;
+        self.last = Some(corrected);
+
+        let fields = [
+            Some(corrected.temperature),
+            corrected.humidity,
+            corrected.pressure,
+            corrected.co2,
+        ];
+        let mut smoothed = [None; 4];
+        for ((window, value), smoothed) in self.windows.iter_mut().zip(fields).zip(&mut smoothed) {
+            if let Some(value) = value {
+                window.push_back(value);
+                while window.len() > self.smoothing.window.max(1) {
+                    window.pop_front();
+                }
+                *smoothed = Some(smooth(window, self.smoothing.method));
+            }
+        }
+        Some(Measurement {
+            temperature: smoothed[0].unwrap_or(corrected.temperature),
+            humidity: smoothed[1],
+            pressure: smoothed[2],
+            co2: smoothed[3],
+        })
+    }
+
+    /// Subtracts the self-heating, a failed heat load reading leaves the temperature as it is
+    fn compensate(&mut self, raw: &Measurement) -> Measurement {
+        let compensation = match &mut self.compensation {
+            Some(compensation) => compensation,
+            None => return *raw,
+        };
+        let load = match compensation.load() {
+            Ok(load) => Some(load),
+            Err(e) if !self.load_failed => {
+                eprintln!("Self-heating is not compensated: {}", e);
+                None
+            }
+            Err(_) => None,
+        };
+        self.load_failed = load.is_none();
+        let temperature = load.map_or(raw.temperature, |load| compensation.apply(raw.temperature, &load));
+        self.load = load;
+        Measurement { temperature, ..*raw }
+    }
+
+    fn calibrate(&self, raw: &Measurement) -> Measurement {
+        Measurement {
+            temperature: self.calibration.temperature.apply(raw.temperature),
+            humidity: raw
+                .humidity
+                .map(|humidity| self.calibration.humidity.apply(humidity).clamp(0.0, 100.0)),
+            ..*raw
+        }
+    }
+}
+
+fn smooth(window: &VecDeque<f32>, method: SmoothingMethod) -> f32 {
+    match method {
+        SmoothingMethod::Mean => window.iter().sum::<f32>() / window.len() as f32,
+        SmoothingMethod::Median => {
+            let mut values = window.iter().copied().collect::<Vec<_>>();
+            values.sort_by(f32::total_cmp);
+            let middle = values.len() / 2;
+            if values.len() % 2 == 0 {
+                (values[middle - 1] + values[middle]) / 2.0
+            } else {
+                values[middle]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::DutyMeter;
+    use std::io::Write;
+
+    fn filter(window: usize, method: SmoothingMethod) -> Filter {
+        let smoothing = model::Smoothing {
+            window,
+            method,
+            max_temperature_jump: 2.0,
+            max_humidity_jump: 10.0,
+        };
+        Filter::new(&model::Calibration::default(), &smoothing, None)
+    }
+
+    fn measurement(temperature: f32, humidity: f32) -> Measurement {
+        Measurement {
+            temperature,
+            humidity: Some(humidity),
+            pressure: None,
+            co2: None,
+        }
+    }
+
+    /// Temperatures after the filter, `None` for the rejected ones
+    fn temperatures(filter: &mut Filter, temperatures: &[f32]) -> Vec<Option<f32>> {
+        temperatures
+            .iter()
+            .map(|&temperature| filter.apply(&measurement(temperature, 50.0)).map(|m| m.temperature))
+            .collect()
+    }
+
+    #[test]
+    fn smooths_with_median() {
+        let mut filter = filter(3, SmoothingMethod::Median);
+        let smoothed = temperatures(&mut filter, &[20.0, 21.0, 20.5, 21.5, 21.0]);
+        assert_eq!(smoothed, [Some(20.0), Some(20.5), Some(20.5), Some(21.0), Some(21.0)]);
+    }
+
+    #[test]
+    fn smooths_with_mean() {
+        let mut filter = filter(3, SmoothingMethod::Mean);
+        let smoothed = temperatures(&mut filter, &[20.0, 21.0, 22.0, 23.0]);
+        assert_eq!(smoothed, [Some(20.0), Some(20.5), Some(21.0), Some(22.0)]);
+    }
+
+    #[test]
+    fn applies_calibration() {
+        let calibration = model::Calibration {
+            temperature: model::Correction { offset: -1.5, gain: 1.0 },
+            humidity: model::Correction { offset: 0.0, gain: 1.1 },
+        };
+        let smoothing = model::Smoothing { window: 1, ..Default::default() };
+        let calibrated = Filter::new(&calibration, &smoothing, None)
+            .apply(&measurement(22.5, 50.0))
+            .unwrap();
+        assert_eq!(calibrated.temperature, 21.0);
+        assert!((calibrated.humidity.unwrap() - 55.0).abs() < 1e-4);
+        // no more than 100%
+        let calibrated = Filter::new(&calibration, &smoothing, None)
+            .apply(&measurement(22.5, 95.0))
+            .unwrap();
+        assert_eq!(calibrated.humidity, Some(100.0));
+    }
+
+    #[test]
+    fn rejects_single_spike() {
+        let mut filter = filter(1, SmoothingMethod::Median);
+        let filtered = temperatures(&mut filter, &[20.0, 30.0, 20.5, 10.0, 21.0]);
+        assert_eq!(filtered, [Some(20.0), None, Some(20.5), None, Some(21.0)]);
+        // humidity jumps too
+        assert!(filter.apply(&measurement(21.0, 70.0)).is_none());
+    }
+
+    #[test]
+    fn accepts_step_after_rejections() {
+        let mut filter = filter(3, SmoothingMethod::Mean);
+        temperatures(&mut filter, &[20.0, 20.0, 20.0]);
+        let mut step = vec![None; MAX_REJECTIONS - 1];
+        // the old values do not drag the new level down
+        step.push(Some(30.0));
+        assert_eq!(temperatures(&mut filter, &[30.0; MAX_REJECTIONS]), step);
+        assert_eq!(temperatures(&mut filter, &[31.0]), [Some(30.5)]);
+    }
+
+    #[test]
+    fn samples_heat_load_without_coefficients() {
+        let mut thermal_zone = tempfile::NamedTempFile::new().unwrap();
+        writeln!(thermal_zone, "48250").unwrap();
+        let config = model::Compensation {
+            thermal_zone: thermal_zone.path().display().to_string(),
+            ..Default::default()
+        };
+        let compensation = Compensation::new(&config, DutyMeter::default());
+        let mut filter = Filter::new(
+            &model::Calibration::default(),
+            &model::Smoothing::default(),
+            Some(compensation),
+        );
+        let raw = Measurement {
+            temperature: 22.5,
+            humidity: Some(40.0),
+            pressure: None,
+            co2: None,
+        };
+        let measurement = filter.apply(&raw).unwrap();
+        assert_eq!(measurement.temperature, 22.5);
+        let load = filter.load().unwrap();
+        assert_eq!((load.display, load.cpu), (0.0, 48.25));
+
+        // the temperature is left as it is when the load cannot be read
+        thermal_zone.close().unwrap();
+        assert_eq!(filter.apply(&raw).unwrap().temperature, 22.5);
+        assert!(filter.load().is_none());
+    }
+}
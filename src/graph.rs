@@ -0,0 +1,137 @@
+use std::time::Duration;
+use tokio::time;
+
+use crate::{
+    display::LinearMatrixDisplay,
+    error::Error,
+    history::{History, Quantity},
+    model::{self, GraphStyle},
+    text,
+};
+
+/// Scrolls the lowest and the highest values, then plots the quantity over the graph span of the history.
+/// Nothing is shown until there are measurements.
+pub(crate) async fn show(
+    display: &mut LinearMatrixDisplay,
+    history: &History,
+    quantity: Quantity,
+    now: i64,
+    config: &model::Config,
+) -> Result<(), Error> {
+    let from = now - config.history.graph_hours.max(1) as i64 * 3600;
+    let series = history
+        .series(quantity, from, now, display.width())
+        .into_iter()
+        .map(|value| value.map(|value| convert(value, quantity, &config.weather)))
+        .collect::<Vec<_>>();
+    let values = series.iter().flatten();
+    let (min, max) = match (
+        values.clone().copied().reduce(f32::min),
+        values.copied().reduce(f32::max),
+    ) {
+        (Some(min), Some(max)) => (min, max),
+        _ => return Ok(()),
+    };
+
+    let label = |value: f32| match quantity {
+        Quantity::Temperature => format!("{:.1}°", value),
+        Quantity::Humidity => format!("{:.0}%", value),
+        Quantity::Pressure | Quantity::Co2 => format!("{:.0}", value),
+    };
+    let labels = format!("min {} max {}", label(min), label(max));
+    text::scroll(display, &labels, config.display.scroll_msec).await?;
+
+    draw(display, &series, min, max, config.history.graph)?;
+    time::sleep(Duration::from_millis(config.display.screen_msec)).await;
+    Ok(())
+}
+
+fn convert(value: f32, quantity: Quantity, config: &model::Weather) -> f32 {
+    match quantity {
+        Quantity::Temperature => config.unit.convert(value),
+        Quantity::Pressure => config.pressure_unit.convert(value),
+        Quantity::Humidity | Quantity::Co2 => value,
+    }
+}
+
+/// One column per value scaled to the display height, the oldest on the left
+fn draw(
+    display: &mut LinearMatrixDisplay,
+    series: &[Option<f32>],
+    min: f32,
+    max: f32,
+    style: GraphStyle,
+) -> Result<(), Error> {
+    let rows = rows(series, min, max);
+    display.draw(|x, y| lit(&rows, x, y, style) as u8)
+}
+
+/// Row of each value, 0 is the top one, a flat series is drawn in the middle
+fn rows(series: &[Option<f32>], min: f32, max: f32) -> Vec<Option<usize>> {
+    series
+        .iter()
+        .map(|value| {
+            value.map(|value| {
+                let level = if max > min { (value - min) / (max - min) } else { 0.5 };
+                7 - (level * 7.0).round() as usize
+            })
+        })
+        .collect()
+}
+
+fn lit(rows: &[Option<usize>], x: usize, y: usize, style: GraphStyle) -> bool {
+    let row = match rows.get(x) {
+        Some(Some(row)) => *row,
+        _ => return false,
+    };
+    match style {
+        GraphStyle::Bar => y >= row,
+        // the line is continued vertically to the previous value
+        GraphStyle::Line => match x.checked_sub(1).and_then(|x| rows[x]) {
+            Some(previous) => y == row || (row.min(previous) < y && y < row.max(previous)),
+            None => y == row,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rows of the display as text, `#` for the lit pixels
+    fn plot(series: &[Option<f32>], min: f32, max: f32, style: GraphStyle) -> Vec<String> {
+        let rows = rows(series, min, max);
+        (0..8)
+            .map(|y| {
+                (0..series.len())
+                    .map(|x| if lit(&rows, x, y, style) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scales_to_display_height() {
+        let series = [Some(10.0), Some(15.0), None, Some(20.0)];
+        assert_eq!(rows(&series, 10.0, 20.0), [Some(7), Some(3), None, Some(0)]);
+        // continued down to the previous value, not across the gap
+        assert_eq!(
+            plot(&series, 10.0, 20.0, GraphStyle::Line),
+            ["...#", "....", "....", ".#..", ".#..", ".#..", ".#..", "#..."]
+        );
+        assert_eq!(
+            plot(&series, 10.0, 20.0, GraphStyle::Bar),
+            ["...#", "...#", "...#", ".#.#", ".#.#", ".#.#", ".#.#", "##.#"]
+        );
+    }
+
+    #[test]
+    fn draws_flat_series_in_middle() {
+        let series = [Some(21.5), None, Some(21.5), Some(21.5)];
+        assert_eq!(rows(&series, 21.5, 21.5), [Some(3), None, Some(3), Some(3)]);
+        assert_eq!(
+            plot(&series, 21.5, 21.5, GraphStyle::Line),
+            ["....", "....", "....", "#.##", "....", "....", "....", "...."]
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::{model, sensor::Measurement};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Quantity {
+    Temperature,
+    Humidity,
+    Pressure,
+    Co2,
+}
+
+/// Running mean of the values got into a bucket
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub(crate) struct Mean {
+    pub sum: f32,
+    pub count: u32,
+}
+
+impl Mean {
+    fn add(&mut self, value: Option<f32>) {
+        if let Some(value) = value {
+            self.sum += value;
+            self.count += 1;
+        }
+    }
+
+    pub fn value(&self) -> Option<f32> {
+        (self.count > 0).then(|| self.sum / self.count as f32)
+    }
+}
+
+/// Measurements averaged over the history resolution
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Bucket {
+    /// unix time of the bucket start
+    pub start: i64,
+    pub temperature: Mean,
+    pub humidity: Mean,
+    pub pressure: Mean,
+    pub co2: Mean,
+}
+
+impl Bucket {
+    pub fn mean(&self, quantity: Quantity) -> &Mean {
+        match quantity {
+            Quantity::Temperature => &self.temperature,
+            Quantity::Humidity => &self.humidity,
+            Quantity::Pressure => &self.pressure,
+            Quantity::Co2 => &self.co2,
+        }
+    }
+}
+
+/// Ring buffer of the latest measurements, the oldest buckets are dropped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct History {
+    /// seconds of a bucket
+    resolution: i64,
+    capacity: usize,
+    buckets: VecDeque<Bucket>,
+}
+
+impl History {
+    pub fn new(config: &model::History) -> Self {
+        let resolution = config.resolution_min.max(1) as i64 * 60;
+        History {
+            resolution,
+            capacity: (config.length_hours as i64 * 3600 / resolution).max(1) as usize,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    /// Adds the measurement taken at the unix time to its bucket
+    pub fn push(&mut self, time: i64, measurement: &Measurement) {
+        let start = time - time.rem_euclid(self.resolution);
+        if self.buckets.back().is_none_or(|bucket| bucket.start < start) {
+            self.buckets.push_back(Bucket {
+                start,
+                temperature: Mean::default(),
+                humidity: Mean::default(),
+                pressure: Mean::default(),
+                co2: Mean::default(),
+            });
+            while self.buckets.len() > self.capacity {
+                self.buckets.pop_front();
+            }
+        }
+        // the time might have been set back, such measurements go to the latest bucket
+        if let Some(bucket) = self.buckets.back_mut() {
+            bucket.temperature.add(Some(measurement.temperature));
+            bucket.humidity.add(measurement.humidity);
+            bucket.pressure.add(measurement.pressure);
+            bucket.co2.add(measurement.co2);
+        }
+    }
+
+    /// Means of the quantity in equal intervals between the unix times, `None` for the ones without measurements
+    pub fn series(&self, quantity: Quantity, from: i64, to: i64, intervals: usize) -> Vec<Option<f32>> {
+        let step = ((to - from) / intervals.max(1) as i64).max(1);
+        let mut means = vec![Mean::default(); intervals];
+        for bucket in self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.start >= from && bucket.start < to)
+        {
+            let mean = bucket.mean(quantity);
+            if let Some(interval) = means.get_mut(((bucket.start - from) / step) as usize) {
+                interval.sum += mean.sum;
+                interval.count += mean.count;
+            }
+        }
+        means.iter().map(Mean::value).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ten-minute buckets over an hour
+    fn history() -> History {
+        History::new(&model::History {
+            resolution_min: 10,
+            length_hours: 1,
+            ..Default::default()
+        })
+    }
+
+    fn measurement(temperature: f32, humidity: Option<f32>) -> Measurement {
+        Measurement { temperature, humidity, pressure: None, co2: None }
+    }
+
+    #[test]
+    fn averages_within_bucket() {
+        let mut history = history();
+        history.push(0, &measurement(20.0, Some(40.0)));
+        history.push(599, &measurement(22.0, None));
+        history.push(600, &measurement(30.0, Some(50.0)));
+        assert_eq!(history.buckets.len(), 2);
+        assert_eq!(
+            history.series(Quantity::Temperature, 0, 1200, 2),
+            [Some(21.0), Some(30.0)]
+        );
+        assert_eq!(history.series(Quantity::Humidity, 0, 1200, 2), [Some(40.0), Some(50.0)]);
+        assert_eq!(history.series(Quantity::Co2, 0, 1200, 2), [None, None]);
+        // both buckets in one interval, weighted by the number of measurements
+        assert_eq!(history.series(Quantity::Temperature, 0, 1200, 1), [Some(24.0)]);
+    }
+
+    #[test]
+    fn leaves_gaps_empty() {
+        let mut history = history();
+        history.push(60, &measurement(20.0, None));
+        history.push(3000, &measurement(25.0, None));
+        assert_eq!(
+            history.series(Quantity::Temperature, 0, 3600, 6),
+            [Some(20.0), None, None, None, None, Some(25.0)]
+        );
+    }
+
+    #[test]
+    fn drops_oldest_buckets() {
+        let mut history = history();
+        for i in 0..8 {
+            history.push(i * 600 + 30, &measurement(i as f32, None));
+        }
+        assert_eq!(history.buckets.len(), 6);
+        assert_eq!(history.buckets.front().map(|bucket| bucket.start), Some(1200));
+        assert_eq!(
+            history.series(Quantity::Temperature, 0, 4800, 8),
+            [
+                None,
+                None,
+                Some(2.0),
+                Some(3.0),
+                Some(4.0),
+                Some(5.0),
+                Some(6.0),
+                Some(7.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_measurements_after_clock_set_back() {
+        let mut history = history();
+        history.push(1200, &measurement(20.0, None));
+        history.push(0, &measurement(22.0, None));
+        assert_eq!(history.buckets.len(), 1);
+        assert_eq!(history.series(Quantity::Temperature, 1200, 1800, 1), [Some(21.0)]);
+    }
+}
@@ -0,0 +1,450 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{self, Error},
+    model::{self, Config, LogFormat},
+    sampler::Reading,
+    sensor::Measurement,
+    time_source,
+};
+
+const COLUMNS: [&str; 10] = [
+    "time",
+    "sensor",
+    "temperature",
+    "humidity",
+    "pressure",
+    "co2",
+    "corrected_temperature",
+    "corrected_humidity",
+    "display",
+    "cpu",
+];
+
+/// Logged measurement of the sensor or a probe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Record {
+    /// unix time
+    pub time: i64,
+    pub sensor: String,
+    /// celsius as measured, `calibrate-sensor` fits the compensation to it
+    pub temperature: f32,
+    pub humidity: Option<f32>,
+    pub pressure: Option<f32>,
+    pub co2: Option<f32>,
+    /// compensated, calibrated and smoothed
+    pub corrected_temperature: f32,
+    pub corrected_humidity: Option<f32>,
+    /// heat load the temperature is compensated for
+    pub display: Option<f32>,
+    pub cpu: Option<f32>,
+}
+
+impl Record {
+    pub fn new(time: i64, sensor: &str, reading: &Reading) -> Option<Self> {
+        let (raw, measurement) = (reading.raw?, reading.measurement?);
+        Some(Record {
+            time,
+            sensor: sensor.to_string(),
+            temperature: raw.temperature,
+            humidity: raw.humidity,
+            pressure: raw.pressure,
+            co2: raw.co2,
+            corrected_temperature: measurement.temperature,
+            corrected_humidity: measurement.humidity,
+            display: reading.load.map(|load| load.display),
+            cpu: reading.load.map(|load| load.cpu),
+        })
+    }
+
+    /// The measurement as it has been shown
+    pub fn measurement(&self) -> Measurement {
+        Measurement {
+            temperature: self.corrected_temperature,
+            humidity: self.corrected_humidity,
+            pressure: self.pressure,
+            co2: self.co2,
+        }
+    }
+
+    fn to_line(&self, format: LogFormat) -> String {
+        match format {
+            LogFormat::Csv => {
+                let value = |value: Option<f32>| value.map(|value| value.to_string()).unwrap_or_default();
+                [
+                    self.time.to_string(),
+                    quote(&self.sensor),
+                    self.temperature.to_string(),
+                    value(self.humidity),
+                    value(self.pressure),
+                    value(self.co2),
+                    self.corrected_temperature.to_string(),
+                    value(self.corrected_humidity),
+                    value(self.display),
+                    value(self.cpu),
+                ]
+                .join(",")
+            }
+            // the record has nothing that cannot be serialized
+            LogFormat::JsonLines => serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+
+    /// `None` for the rows cut off by a power loss and alike
+    fn from_csv(header: &[&str], line: &str) -> Option<Self> {
+        let values = split(line);
+        let text = |name: &str| {
+            let column = header.iter().position(|column| *column == name)?;
+            values.get(column).map(|value| value.trim())
+        };
+        let value = |name: &str| text(name).filter(|value| !value.is_empty());
+        let optional = |name: &str| value(name).map(|value| value.parse::<f32>().ok());
+        Some(Record {
+            time: value("time")?.parse().ok()?,
+            sensor: text("sensor")?.to_string(),
+            temperature: value("temperature")?.parse().ok()?,
+            humidity: optional("humidity").unwrap_or_default(),
+            pressure: optional("pressure").unwrap_or_default(),
+            co2: optional("co2").unwrap_or_default(),
+            corrected_temperature: value("corrected_temperature")?.parse().ok()?,
+            corrected_humidity: optional("corrected_humidity").unwrap_or_default(),
+            display: optional("display").unwrap_or_default(),
+            cpu: optional("cpu").unwrap_or_default(),
+        })
+    }
+}
+
+/// The field quoted when it has commas or quotes, e.g. a probe named `greenhouse, north`
+fn quote(field: &str) -> String {
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Fields of a CSV line, the quoted ones may have commas and doubled quotes in them
+pub(crate) fn split(line: &str) -> Vec<String> {
+    let (mut fields, mut field) = (vec![], String::new());
+    let (mut quoted, mut chars) = (false, line.chars().peekable());
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Appends the records to a file per day, the day is split into numbered files above the size limit
+pub(crate) struct Logger {
+    config: model::Log,
+    /// date and path of the open file
+    file: Option<(NaiveDate, String, File)>,
+}
+
+impl Logger {
+    pub fn new(config: &model::Log) -> Self {
+        Logger { config: config.clone(), file: None }
+    }
+
+    pub fn write(&mut self, time: &DateTime<Local>, records: &[Record]) -> Result<(), Error> {
+        let date = time.date().naive_local();
+        let max_size = self.config.max_file_kb.max(1) * 1024;
+        let rotate = match &self.file {
+            Some((day, _, file)) => *day != date || file.metadata().map_or(true, |metadata| metadata.len() >= max_size),
+            None => true,
+        };
+        if rotate {
+            self.open(date, max_size)?;
+        }
+        if let Some((_, path, file)) = &mut self.file {
+            for record in records {
+                writeln!(file, "{}", record.to_line(self.config.format)).context(error::LogSnafu { path: &*path })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens the first file of the date with some room left, the old files are removed when the day changes
+    fn open(&mut self, date: NaiveDate, max_size: u64) -> Result<(), Error> {
+        let directory = Path::new(&self.config.directory);
+        fs::create_dir_all(directory).context(error::LogSnafu { path: &self.config.directory })?;
+        if self.file.as_ref().is_none_or(|(day, _, _)| *day != date) {
+            remove_old(&self.config, date)?;
+        }
+        let path = (0..)
+            .map(|part| directory.join(file_name(date, part, self.config.format)))
+            .find(|path| fs::metadata(path).map_or(true, |metadata| metadata.len() < max_size))
+            .unwrap_or_default()
+            .display()
+            .to_string();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .context(error::LogSnafu { path: &path })?;
+        let empty = file.metadata().is_ok_and(|metadata| metadata.len() == 0);
+        if empty && self.config.format == LogFormat::Csv {
+            writeln!(file, "{}", COLUMNS.join(",")).context(error::LogSnafu { path: &path })?;
+        }
+        self.file = Some((date, path, file));
+        Ok(())
+    }
+}
+
+/// `2021-12-31.csv`, `2021-12-31.1.csv` and so on
+fn file_name(date: NaiveDate, part: usize, format: LogFormat) -> String {
+    let extension = match format {
+        LogFormat::Csv => "csv",
+        LogFormat::JsonLines => "jsonl",
+    };
+    match part {
+        0 => format!("{}.{}", date, extension),
+        _ => format!("{}.{}.{}", date, part, extension),
+    }
+}
+
+/// Log files in the directory with their dates and formats
+fn files(directory: &Path) -> Result<Vec<(NaiveDate, LogFormat, PathBuf)>, io::Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let format = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => LogFormat::Csv,
+            Some("jsonl") => LogFormat::JsonLines,
+            _ => continue,
+        };
+        let date = path
+            .file_name()
+            .and_then(|name| name.to_str()?.get(..10))
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+        if let Some(date) = date {
+            files.push((date, format, path));
+        }
+    }
+    Ok(files)
+}
+
+fn remove_old(config: &model::Log, today: NaiveDate) -> Result<(), Error> {
+    let oldest = today - Duration::days(config.retention_days as i64);
+    let files = files(Path::new(&config.directory)).context(error::LogReadSnafu { path: &config.directory })?;
+    for (_, _, path) in files.into_iter().filter(|(date, _, _)| *date < oldest) {
+        fs::remove_file(&path).context(error::LogSnafu { path: path.display().to_string() })?;
+    }
+    Ok(())
+}
+
+/// Records taken from `from` till `to` in both formats, ordered by the time
+pub(crate) fn read(config: &model::Log, from: &DateTime<Local>, to: &DateTime<Local>) -> Result<Vec<Record>, Error> {
+    let directory = Path::new(&config.directory);
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+    let context = || error::LogReadSnafu { path: &config.directory };
+    let days = from.date().naive_local()..=to.date().naive_local();
+    let mut records = Vec::new();
+    for (_, format, path) in files(directory)
+        .context(context())?
+        .into_iter()
+        .filter(|(date, _, _)| days.contains(date))
+    {
+        let text = fs::read_to_string(&path).context(context())?;
+        match format {
+            LogFormat::Csv => {
+                let mut lines = text.lines();
+                let header = split(lines.next().unwrap_or_default());
+                let header = header.iter().map(|column| column.trim()).collect::<Vec<_>>();
+                records.extend(lines.filter_map(|line| Record::from_csv(&header, line)));
+            }
+            LogFormat::JsonLines => records.extend(text.lines().filter_map(|line| serde_json::from_str(line).ok())),
+        }
+    }
+    records.retain(|record| record.time >= from.timestamp() && record.time < to.timestamp());
+    records.sort_by_key(|record| record.time);
+    Ok(records)
+}
+
+/// `history export` prints the logged records
+pub(crate) fn command(config: &Config, opts: &clap::ArgMatches) -> Result<(), Error> {
+    let log = config.log.as_ref().ok_or(Error::LogConfigError {})?;
+    if let ("export", Some(opts)) = opts.subcommand() {
+        let time = |option: &str, default: DateTime<Local>| match opts.value_of(option) {
+            Some(value) => time_source::parse_local(value).ok_or_else(|| error::TimeOptionSnafu { value }.build()),
+            None => Ok(default),
+        };
+        let from = time("from", Local.timestamp(0, 0))?;
+        let to = time("to", Local::now())?;
+        let format = match opts.value_of("format").unwrap_or("csv") {
+            "csv" => LogFormat::Csv,
+            "json-lines" => LogFormat::JsonLines,
+            format => return error::LogFormatSnafu { format }.fail(),
+        };
+
+        if format == LogFormat::Csv {
+            println!("{}", COLUMNS.join(","));
+        }
+        for record in read(log, &from, &to)? {
+            println!("{}", record.to_line(format));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(directory: &Path, format: LogFormat) -> model::Log {
+        model::Log {
+            directory: directory.display().to_string(),
+            format,
+            max_file_kb: 1,
+            retention_days: 2,
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.ymd(2022, 1, day).and_hms(hour, minute, 0)
+    }
+
+    fn record(time: &DateTime<Local>, sensor: &str) -> Record {
+        Record {
+            time: time.timestamp(),
+            sensor: sensor.to_string(),
+            temperature: 23.25,
+            humidity: Some(41.5),
+            pressure: None,
+            co2: Some(812.0),
+            corrected_temperature: 21.1,
+            corrected_humidity: Some(45.0),
+            display: Some(0.35),
+            cpu: None,
+        }
+    }
+
+    /// Names of the files in the directory, sorted
+    fn names(directory: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn writes_file_per_day() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut logger = Logger::new(&config(directory.path(), LogFormat::Csv));
+        for time in [at(15, 23, 58), at(15, 23, 59), at(16, 0, 0)] {
+            logger.write(&time, &[record(&time, "aht10")]).unwrap();
+        }
+        assert_eq!(names(directory.path()), ["2022-01-15.csv", "2022-01-16.csv"]);
+        let text = fs::read_to_string(directory.path().join("2022-01-15.csv")).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], COLUMNS.join(","));
+    }
+
+    #[test]
+    fn splits_day_above_max_size() {
+        let directory = tempfile::tempdir().unwrap();
+        let config = config(directory.path(), LogFormat::Csv);
+        let mut logger = Logger::new(&config);
+        let time = at(15, 12, 0);
+        for _ in 0..50 {
+            logger.write(&time, &[record(&time, "aht10")]).unwrap();
+        }
+        let names = names(directory.path());
+        assert_eq!(names, ["2022-01-15.1.csv", "2022-01-15.2.csv", "2022-01-15.csv"]);
+        for name in &names {
+            let text = fs::read_to_string(directory.path().join(name)).unwrap();
+            assert!(text.starts_with(COLUMNS[0]), "{}", name);
+            // a file is closed after the write reaching the limit
+            assert!(text.len() < 1024 + record(&time, "aht10").to_line(LogFormat::Csv).len() + 1);
+        }
+        assert_eq!(read(&config, &at(15, 0, 0), &at(16, 0, 0)).unwrap().len(), 50);
+
+        // the parts with room left are reused after a restart
+        let mut logger = Logger::new(&config);
+        logger.write(&time, &[record(&time, "aht10")]).unwrap();
+        assert_eq!(self::names(directory.path()), names);
+    }
+
+    #[test]
+    fn removes_old_files_when_day_changes() {
+        let directory = tempfile::tempdir().unwrap();
+        for name in [
+            "2022-01-12.csv",
+            "2022-01-12.1.csv",
+            "2022-01-13.jsonl",
+            "2022-01-14.csv",
+            "notes.txt",
+        ] {
+            fs::write(directory.path().join(name), "").unwrap();
+        }
+        let mut logger = Logger::new(&config(directory.path(), LogFormat::Csv));
+        let time = at(15, 23, 59);
+        logger.write(&time, &[record(&time, "aht10")]).unwrap();
+        assert_eq!(
+            names(directory.path()),
+            ["2022-01-13.jsonl", "2022-01-14.csv", "2022-01-15.csv", "notes.txt"]
+        );
+
+        let time = at(16, 0, 0);
+        logger.write(&time, &[record(&time, "aht10")]).unwrap();
+        assert_eq!(
+            names(directory.path()),
+            ["2022-01-14.csv", "2022-01-15.csv", "2022-01-16.csv", "notes.txt"]
+        );
+    }
+
+    #[test]
+    fn reads_back_what_is_written() {
+        for format in [LogFormat::Csv, LogFormat::JsonLines] {
+            let directory = tempfile::tempdir().unwrap();
+            let config = model::Log {
+                max_file_kb: 1024,
+                ..config(directory.path(), format)
+            };
+            let mut logger = Logger::new(&config);
+            let (first, second) = (at(15, 12, 0), at(16, 9, 30));
+            let written = [
+                record(&first, "aht10"),
+                record(&first, "greenhouse, \"north\""),
+                record(&second, "outside"),
+            ];
+            logger.write(&first, &written[..2]).unwrap();
+            logger.write(&second, &written[2..]).unwrap();
+
+            let records = read(&config, &at(15, 0, 0), &at(17, 0, 0)).unwrap();
+            assert_eq!(format!("{:?}", records), format!("{:?}", written), "{:?}", format);
+            // only the time span asked for
+            assert_eq!(read(&config, &at(16, 0, 0), &at(17, 0, 0)).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(quote("outside"), "outside");
+        assert_eq!(quote("greenhouse, \"north\""), "\"greenhouse, \"\"north\"\"\"");
+        assert_eq!(
+            split("1,\"greenhouse, \"\"north\"\"\",,2"),
+            ["1", "greenhouse, \"north\"", "", "2"]
+        );
+    }
+}
@@ -1,21 +1,66 @@
 //mod aht10;
+mod agenda;
 mod aht10;
+mod bme280;
+mod buzzer;
 mod clock;
+mod colon;
+mod comfort;
+mod compensation;
+mod control;
+mod countdown;
+mod crc;
 mod display;
+mod ds18b20;
+mod ds3231;
 mod error;
+mod faces;
+mod filter;
+mod graph;
+mod history;
+mod logger;
+mod mhz19;
 mod model;
+mod moon;
+mod profile;
+mod rtc;
+mod sampler;
+mod scd4x;
+mod scheduler;
+mod screens;
+mod sensor;
+mod sht;
+mod sun;
+mod text;
+mod time_source;
+mod timer;
 mod weather;
 
-use aht10::AHT10;
 use clap::{load_yaml, App, AppSettings};
-use embedded_hal::prelude::_embedded_hal_blocking_delay_DelayMs;
-use linux_embedded_hal::{Delay, I2cdev};
 use model::Config;
-use snafu::ResultExt;
-use std::path::Path;
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use sysfs_pwm::Pwm;
+use tokio::time;
 
-use crate::{display::LinearMatrixDisplay, weather::WeatherType};
+use crate::{
+    buzzer::Buzzer,
+    colon::Colon,
+    display::LinearMatrixDisplay,
+    ds18b20::Ds18b20,
+    faces::Faces,
+    profile::Profiles,
+    sampler::Sampler,
+    scheduler::Scheduler,
+    screens::Screens,
+    sensor::{Sensor, WithCo2},
+    time_source::TimeSource,
+    timer::Timer,
+    weather::Observations,
+};
 
 #[macro_use]
 extern crate bitflags;
@@ -36,10 +81,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         ("test-lirc", Some(opts)) => test_lirc(opts)?,
 
+        ("rtc", Some(sub_opts)) => {
+            let config_location = opts.value_of("config").unwrap_or("./clock.yaml");
+            rtc::command(&Config::from_yaml(Path::new(config_location))?, sub_opts)?
+        }
+
+        ("history", Some(sub_opts)) => {
+            let config_location = opts.value_of("config").unwrap_or("./clock.yaml");
+            logger::command(&Config::from_yaml(Path::new(config_location))?, sub_opts)?
+        }
+
+        ("calibrate-sensor", Some(sub_opts)) => {
+            let config_location = opts.value_of("config").unwrap_or("./clock.yaml");
+            calibrate_sensor(Path::new(config_location), sub_opts)?
+        }
+
+        ("timer", Some(sub_opts)) | ("stopwatch", Some(sub_opts)) => {
+            let config_location = opts.value_of("config").unwrap_or("./clock.yaml");
+            let mode = opts.subcommand_name().unwrap_or_default();
+            send_command(Path::new(config_location), mode, sub_opts)?
+        }
+
         _ => {
             let config_location = opts.value_of("config").unwrap_or("./clock.yaml");
             let do_clean = opts.is_present("clean");
-            run(&Path::new(&config_location), do_clean).await?;
+            let time_source = time_source::from_options(opts.value_of("fake-time"), opts.value_of("time-scale"))?;
+            run(&Path::new(&config_location), do_clean, time_source).await?;
         }
     }
 
@@ -103,7 +170,43 @@ fn test_lirc(opts: &clap::ArgMatches) -> Result<(), error::Error> {
     Ok(())
 }
 
-async fn run(config_location: &Path, do_clean: bool) -> Result<(), error::Error> {
+fn calibrate_sensor(config_location: &Path, opts: &clap::ArgMatches) -> Result<(), error::Error> {
+    let mut config = Config::from_yaml(config_location)?;
+    let samples = compensation::read_log(Path::new(opts.value_of("LOG").unwrap()))?;
+    let current = config.weather.compensation.clone().unwrap_or_default();
+    let fitted = compensation::fit(&samples, &current)?;
+    println!(
+        "{} samples, RMS error {:.2}°C -> {:.2}°C",
+        samples.len(),
+        compensation::rms_error(&samples, &current),
+        compensation::rms_error(&samples, &fitted)
+    );
+    println!(
+        "display: {:.3}, cpu: {:.3}, offset: {:.3}",
+        fitted.display, fitted.cpu, fitted.offset
+    );
+    if opts.is_present("save") {
+        config.weather.compensation = Some(fitted);
+        config.to_yaml(config_location)?;
+    }
+    Ok(())
+}
+
+fn send_command(config_location: &Path, mode: &str, opts: &clap::ArgMatches) -> Result<(), error::Error> {
+    let config = Config::from_yaml(config_location)?;
+    let mut command = format!("{} {}", mode, opts.value_of("ACTION").unwrap());
+    if let Some(duration) = opts.value_of("DURATION") {
+        command = format!("{} {}", command, duration);
+    }
+    println!("{}", control::send(&config.control.socket, &command)?);
+    Ok(())
+}
+
+async fn run(
+    config_location: &Path,
+    do_clean: bool,
+    time_source: Option<Arc<dyn TimeSource>>,
+) -> Result<(), error::Error> {
     // read config
     let config = Config::from_yaml(config_location)?;
 
@@ -124,33 +227,134 @@ async fn run(config_location: &Path, do_clean: bool) -> Result<(), error::Error>
         &config.display.gpio_dev, config.display.data_pin, config.display.cs_pin, config.display.clk_pin
     );
 
-    let mut d = Delay;
-    // initialize humidity and temperature sensor
-    let mut sensor = AHT10 {
-        i2c: I2cdev::new(&config.weather.sensor.gpio_dev).context(error::I2CSnafu)?,
-        delay_ms: |ms| Delay {}.delay_ms(ms),
+    // simulated time goes first, then the system one backed by RTC
+    let time_source = match time_source {
+        Some(time_source) => time_source,
+        None => rtc::time_source(&config.rtc),
     };
-    sensor.init().context(error::SensorSnafu)?;
 
-    // draw in cycle
+    // initialize humidity and temperature sensor, it is measured in the background,
+    // the clock goes on without it showing its readings as unknown
+    let open = |config: &model::WeatherSensor| {
+        sensor::open(config)
+            .map_err(|e| eprintln!("Sensor `{}` is not available: {}", config.driver, e))
+            .ok()
+    };
+    let sensor = match (open(&config.weather.sensor), &config.weather.co2_sensor) {
+        (Some(sensor), Some(co2_sensor)) => match open(co2_sensor) {
+            Some(co2) => {
+                println!("CO2 is measured by `{}`", co2_sensor.driver);
+                Some(Box::new(WithCo2::new(sensor, co2)) as Box<dyn Sensor>)
+            }
+            None => Some(sensor),
+        },
+        (sensor, _) => sensor,
+    };
+    let probes = Ds18b20::open_all(&config.weather)?;
+    println!(
+        "Sensor `{}` and {} probe(s) are measured every {} s",
+        config.weather.sensor.driver,
+        probes.len(),
+        config.weather.sample_interval_sec
+    );
+    let sampler = Sampler::spawn(sensor, probes, &config, display.duty_meter(), time_source.clone());
+
+    let mut buzzer = config.buzzer.as_ref().map(Buzzer::new).transpose()?;
+    let commands = control::listen(&config.control)?;
+    let mut timer: Option<Timer> = None;
+    let mut faces = Faces::new(&config.faces, config.display.slim)?;
+    let mut profiles = Profiles::new(&config.profiles, config.location.as_ref(), config.display.brightness)?;
+    let mut screens = Screens::new(&config, &sampler)?;
+    let mut colon = Colon::new();
+
+    // draw in cycle, aligned to the wall clock
+    let mut scheduler = Scheduler::new(time_source.clone());
     let mut weather_interwal_counter = 0;
+    let mut observations = Observations::new(&config.weather);
     loop {
-        if weather_interwal_counter >= config.weather.display_interval_sec {
-            weather_interwal_counter = 0;
+        let tick = scheduler.tick().await;
+        observations.observe(&sampler, &tick.time);
+        if let Some(brightness) = profiles.update(&tick.time) {
+            display.brightness(brightness)?;
+        }
+
+        for request in commands.try_iter() {
+            let result = timer::apply(&mut timer, &request.command, &config.timer, Instant::now());
+            if let Err(e) = &result {
+                eprintln!("{}", e);
+            }
+            request.reply(result);
+        }
+
+        if let Some(t) = &timer {
+            // countdown and stopwatch take over the display, the buzzer rings in time with the blinking
+            let ringing = t
+                .overrun(Instant::now())
+                .map(|o| o.as_millis() < config.timer.ring_msec as u128);
+            if ringing == Some(false) {
+                timer = None;
+            } else {
+                if let Some(buzzer) = &mut buzzer {
+                    buzzer.set(ringing == Some(true) && tick.first_half)?;
+                }
+                t.draw(&mut display, Instant::now(), tick.first_half, config.display.slim)?;
+                continue;
+            }
+        }
+        if let Some(buzzer) = &mut buzzer {
+            buzzer.set(false)?;
+        }
 
-            weather::draw(&mut display, &mut sensor, WeatherType::Temperature, config.display.slim)?;
-            d.delay_ms(config.weather.temperature_on_display_msec);
+        if screens.notify(&mut display, &tick.time).await? {
+            continue;
+        }
 
-            weather::draw(&mut display, &mut sensor, WeatherType::Humidity, config.display.slim)?;
-            d.delay_ms(config.weather.humidity_on_display_msec);
-        } else {
+        if tick.jumped {
+            // show the corrected time right away
+            weather_interwal_counter = 0;
+        } else if tick.first_half {
             weather_interwal_counter += 1;
+        }
+        if weather_interwal_counter > config.weather.display_interval_sec && !profiles.night() {
+            weather_interwal_counter = 0;
 
-            clock::draw(&mut display, true, config.display.slim)?;
-            d.delay_ms(500u32);
+            match weather::show(
+                &mut display,
+                &sampler,
+                &mut observations,
+                &config.weather,
+                config.display.slim,
+            )
+            .await
+            {
+                Ok(Some(alert)) => weather::alert(&mut display, buzzer.as_mut(), alert).await?,
+                Ok(None) => {}
+                Err(e) => return Err(e),
+            }
+
+            for screen in &config.screens {
+                screens.show(&mut display, *screen, &tick.time, &observations).await?;
+            }
+        } else {
+            faces.rotate(&tick.time);
+            let colon_mode = profiles
+                .colon()
+                .or_else(|| faces.colon())
+                .unwrap_or(config.display.colon);
+            let frames = colon.frames(colon_mode, tick.first_half, time_source.synchronized());
 
-            clock::draw(&mut display, false, config.display.slim)?;
-            d.delay_ms(500u32);
+            let face = faces.face();
+            if let Some(text) = face.scroll_text(&tick.time) {
+                text::scroll(&mut display, &text, config.display.scroll_msec).await?;
+            }
+            // more than one frame dithers the colon, they fill the half of a second
+            let frame_duration = Duration::from_millis(500) / frames.len() as u32;
+            for (i, draw_dots) in frames.iter().enumerate() {
+                if i > 0 {
+                    time::sleep(frame_duration).await;
+                }
+                face.draw(&mut display, &tick.time, *draw_dots)?;
+            }
         }
     }
 }
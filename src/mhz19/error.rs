@@ -0,0 +1,20 @@
+use snafu::Snafu;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum Error {
+    #[snafu(display("MH-Z19 UART setup error"))]
+    UartError { source: std::io::Error },
+
+    #[snafu(display("MH-Z19 read error"))]
+    ReadError { source: std::io::Error },
+
+    #[snafu(display("MH-Z19 write error"))]
+    WriteError { source: std::io::Error },
+
+    #[snafu(display("MH-Z19 data checksum mismatch"))]
+    ChecksumError {},
+
+    #[snafu(display("MH-Z19 does not respond to command 0x{:02X}", command))]
+    ResponseError { command: u8 },
+}
@@ -0,0 +1,186 @@
+pub mod error;
+
+use error::Error;
+use snafu::ResultExt;
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    os::unix::io::AsRawFd,
+};
+
+const START: u8 = 0xFF;
+const SENSOR: u8 = 0x01;
+const READ_CO2: u8 = 0x86;
+
+/// Bytes skipped looking for the start of the response, a frame and a half
+const SYNC_BYTES: usize = 14;
+
+/// Winsen MH-Z19B NDIR CO2 sensor on a serial port (or anything else speaking its protocol)
+pub struct Mhz19<S> {
+    serial: S,
+}
+
+impl Mhz19<File> {
+    /// Opens the UART (`/dev/ttyS0`, `/dev/serial0`) as 9600 8N1 with a second of read timeout
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let serial = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .context(error::UartSnafu)?;
+        // SAFETY: `termios` is plain data the zeroed value is valid for, it is filled by `tcgetattr`
+        // before use; `fd` belongs to `serial` which stays open over the calls
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            let fd = serial.as_raw_fd();
+            if libc::tcgetattr(fd, &mut termios) != 0 {
+                return Err(std::io::Error::last_os_error()).context(error::UartSnafu);
+            }
+            libc::cfmakeraw(&mut termios);
+            libc::cfsetispeed(&mut termios, libc::B9600);
+            libc::cfsetospeed(&mut termios, libc::B9600);
+            termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+            termios.c_cflag &= !(libc::CSTOPB | libc::PARENB);
+            // the read returns what it has got after a second of silence
+            termios.c_cc[libc::VMIN] = 0;
+            termios.c_cc[libc::VTIME] = 10;
+            if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 || libc::tcflush(fd, libc::TCIOFLUSH) != 0 {
+                return Err(std::io::Error::last_os_error()).context(error::UartSnafu);
+            }
+        }
+        Ok(Mhz19::new(serial))
+    }
+}
+
+impl<S: Read + Write> Mhz19<S> {
+    pub fn new(serial: S) -> Self {
+        Mhz19 { serial }
+    }
+
+    /// Measures CO2 (ppm) and the rough temperature of the sensor internals (celsius)
+    pub fn measure(&mut self) -> Result<(u16, f32), Error> {
+        let response = self.request(READ_CO2)?;
+        let co2 = u16::from_be_bytes([response[2], response[3]]);
+        let temperature = response[4] as f32 - 40.0;
+        Ok((co2, temperature))
+    }
+
+    /// Sends the command and reads the 9-byte response to it
+    fn request(&mut self, command: u8) -> Result<[u8; 9], Error> {
+        let mut frame = [START, SENSOR, command, 0, 0, 0, 0, 0, 0];
+        frame[8] = checksum(&frame);
+        self.serial.write_all(&frame).context(error::WriteSnafu)?;
+        self.serial.flush().context(error::WriteSnafu)?;
+
+        // the response starts with 0xFF and the command, the leftovers of a previous one are skipped
+        let mut response = [0; 9];
+        let mut skipped = 0;
+        loop {
+            self.serial.read_exact(&mut response[..1]).context(error::ReadSnafu)?;
+            if response[0] == START {
+                self.serial.read_exact(&mut response[1..2]).context(error::ReadSnafu)?;
+                if response[1] == command {
+                    break;
+                }
+            }
+            skipped += 1;
+            if skipped > SYNC_BYTES {
+                return Err(Error::ResponseError { command });
+            }
+        }
+        self.serial.read_exact(&mut response[2..]).context(error::ReadSnafu)?;
+        if checksum(&response) != response[8] {
+            return Err(Error::ChecksumError {});
+        }
+        Ok(response)
+    }
+}
+
+/// Negated sum of the bytes between the start byte and the checksum
+pub fn checksum(frame: &[u8; 9]) -> u8 {
+    let sum = frame[1..8].iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    (!sum).wrapping_add(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::VecDeque, io};
+
+    /// Serial port replaying the recorded bytes
+    #[derive(Default)]
+    struct Serial {
+        written: Vec<u8>,
+        received: VecDeque<u8>,
+    }
+
+    impl Read for Serial {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            // nothing read after the timeout
+            let count = buf.len().min(self.received.len());
+            for (byte, received) in buf.iter_mut().zip(self.received.drain(..count)) {
+                *byte = received;
+            }
+            Ok(count)
+        }
+    }
+
+    impl Write for Serial {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Response of a sensor to the CO2 request: 608 ppm at 31°C
+    const RESPONSE: [u8; 9] = [0xFF, 0x86, 0x02, 0x60, 0x47, 0x00, 0x00, 0x00, 0xD1];
+
+    fn replaying(received: &[u8]) -> Mhz19<Serial> {
+        Mhz19::new(Serial {
+            written: vec![],
+            received: received.iter().copied().collect(),
+        })
+    }
+
+    #[test]
+    fn reads_co2() {
+        let mut sensor = replaying(&RESPONSE);
+        assert_eq!(sensor.measure().unwrap(), (608, 31.0));
+        assert_eq!(
+            sensor.serial.written,
+            [0xFF, 0x01, 0x86, 0x00, 0x00, 0x00, 0x00, 0x00, 0x79]
+        );
+        assert_eq!(checksum(&RESPONSE), RESPONSE[8]);
+    }
+
+    #[test]
+    fn skips_leftovers() {
+        // the tail of a response read too late and a start byte of the garbage
+        let received = [&[0x00, 0x00, 0xD1, 0xFF, 0x99][..], &RESPONSE].concat();
+        let mut sensor = replaying(&received);
+        assert_eq!(sensor.measure().unwrap(), (608, 31.0));
+        assert!(sensor.serial.received.is_empty());
+
+        let received = [&[0x00; SYNC_BYTES + 1][..], &RESPONSE].concat();
+        assert!(matches!(
+            replaying(&received).measure(),
+            Err(Error::ResponseError { command: READ_CO2 })
+        ));
+    }
+
+    #[test]
+    fn rejects_broken_responses() {
+        let mut corrupted = RESPONSE;
+        corrupted[3] ^= 0x10;
+        assert!(matches!(replaying(&corrupted).measure(), Err(Error::ChecksumError {})));
+        assert!(matches!(
+            replaying(&RESPONSE[..5]).measure(),
+            Err(Error::ReadError { .. })
+        ));
+        assert!(matches!(replaying(&[]).measure(), Err(Error::ReadError { .. })));
+    }
+}
@@ -1,13 +1,42 @@
 use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
-use std::{fs, path::Path};
+use std::{collections::BTreeMap, fs, path::Path};
 
 use crate::error::{self, Error};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     pub display: Display,
+    #[serde(default)]
+    pub faces: Faces,
     pub weather: Weather,
+    #[serde(default)]
+    pub timer: Timer,
+    #[serde(default)]
+    pub buzzer: Option<Buzzer>,
+    #[serde(default)]
+    pub control: Control,
+    #[serde(default)]
+    pub rtc: Option<Rtc>,
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    #[serde(default)]
+    pub location: Option<Location>,
+    /// screens shown in turn after the weather ones
+    #[serde(default)]
+    pub screens: Vec<Screen>,
+    #[serde(default)]
+    pub history: History,
+    /// readings appended to files, the history is restored from them on start
+    #[serde(default)]
+    pub log: Option<Log>,
+    #[serde(default)]
+    pub countdown: Countdown,
+    #[serde(default)]
+    pub agenda: Option<Agenda>,
+    /// language of the texts on the display
+    #[serde(default)]
+    pub language: Language,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,6 +48,47 @@ pub struct Display {
     pub number_of_matrices: u32,
     pub brightness: u8,
     pub slim: bool,
+    /// delay between steps of scrolling text
+    #[serde(default = "default_scroll_msec")]
+    pub scroll_msec: u64,
+    /// how long a static screen stays on the display
+    #[serde(default = "default_screen_msec")]
+    pub screen_msec: u64,
+    #[serde(default)]
+    pub colon: ColonMode,
+}
+
+/// How the separator between hours and minutes is shown
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColonMode {
+    Steady,
+    /// 1 Hz blinking
+    #[default]
+    Blink,
+    /// steady while the system time is synchronised, blinking otherwise
+    BlinkUnsynced,
+    /// smooth "breathing" brightness changes
+    Pulse,
+}
+
+fn default_scroll_msec() -> u64 {
+    40
+}
+
+fn default_screen_msec() -> u64 {
+    2000
+}
+
+/// Clock faces: `digits`, `slim`, `binary`, `bcd`, `fuzzy`, `bar`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Faces {
+    pub rotation: Vec<String>,
+    /// switch to the next face after this number of seconds, 0 - never
+    pub rotate_sec: u32,
+    /// colon mode of the face by its name, overrides `display.colon`
+    #[serde(default)]
+    pub colon: BTreeMap<String, ColonMode>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,12 +96,614 @@ pub struct Weather {
     pub display_interval_sec: u8,
     pub humidity_on_display_msec: u64,
     pub temperature_on_display_msec: u64,
+    #[serde(default)]
+    pub unit: TemperatureUnit,
+    #[serde(default = "default_pressure_on_display_msec")]
+    pub pressure_on_display_msec: u64,
+    #[serde(default)]
+    pub pressure_unit: PressureUnit,
+    #[serde(default = "default_co2_on_display_msec")]
+    pub co2_on_display_msec: u64,
+    #[serde(default)]
+    pub co2: Co2,
+    /// the sensor and the probes are measured in the background with this period
+    #[serde(default = "default_sample_interval_sec")]
+    pub sample_interval_sec: u64,
+    /// older measurements are shown as `--.-`
+    #[serde(default = "default_stale_after_sec")]
+    pub stale_after_sec: u64,
+    #[serde(default)]
+    pub smoothing: Smoothing,
+    #[serde(default)]
+    pub trend: Trend,
+    /// self-heating of the sensor, fitted by `calibrate-sensor`
+    #[serde(default)]
+    pub compensation: Option<Compensation>,
     pub sensor: WeatherSensor,
+    /// CO2 sensor (`scd4x`, `mhz19`) next to the main one, only its CO2 is shown
+    #[serde(default)]
+    pub co2_sensor: Option<WeatherSensor>,
+    /// DS18B20 one-wire probes shown next to the sensor temperature
+    #[serde(default)]
+    pub probes: Vec<Probe>,
+    #[serde(default = "default_w1_devices")]
+    pub w1_devices: String,
+}
+
+fn default_pressure_on_display_msec() -> u64 {
+    1500
+}
+
+/// Celsius the sensor reads above the air, subtracted from its raw temperature:
+/// `display * duty + cpu * (CPU temperature - sensor temperature) + offset`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Compensation {
+    /// at the full display duty (all pixels lit at the maximum brightness)
+    #[serde(default)]
+    pub display: f32,
+    /// share of the CPU excess over the sensor temperature
+    #[serde(default)]
+    pub cpu: f32,
+    #[serde(default)]
+    pub offset: f32,
+    #[serde(default = "default_thermal_zone")]
+    pub thermal_zone: String,
+    /// the display duty is averaged over this time, the enclosure warms up slowly
+    #[serde(default = "default_thermal_time_constant_sec")]
+    pub time_constant_sec: u64,
+}
+
+impl Default for Compensation {
+    fn default() -> Self {
+        Compensation {
+            display: 0.0,
+            cpu: 0.0,
+            offset: 0.0,
+            thermal_zone: default_thermal_zone(),
+            time_constant_sec: default_thermal_time_constant_sec(),
+        }
+    }
+}
+
+fn default_thermal_zone() -> String {
+    "/sys/class/thermal/thermal_zone0/temp".to_string()
+}
+
+fn default_thermal_time_constant_sec() -> u64 {
+    600
+}
+
+/// Filter of the measurements, applied after the calibration
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Smoothing {
+    /// number of the latest measurements to smooth, 1 turns smoothing off
+    #[serde(default = "default_smoothing_window")]
+    pub window: usize,
+    #[serde(default)]
+    pub method: SmoothingMethod,
+    /// celsius, bigger changes between measurements are rejected until they repeat
+    #[serde(default = "default_max_temperature_jump")]
+    pub max_temperature_jump: f32,
+    /// percents of relative humidity
+    #[serde(default = "default_max_humidity_jump")]
+    pub max_humidity_jump: f32,
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Smoothing {
+            window: default_smoothing_window(),
+            method: SmoothingMethod::default(),
+            max_temperature_jump: default_max_temperature_jump(),
+            max_humidity_jump: default_max_humidity_jump(),
+        }
+    }
+}
+
+fn default_smoothing_window() -> usize {
+    1
+}
+
+fn default_max_temperature_jump() -> f32 {
+    5.0
+}
+
+fn default_max_humidity_jump() -> f32 {
+    20.0
+}
+
+/// Arrows next to the temperature and the humidity
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Trend {
+    /// minutes of the readings compared
+    #[serde(default = "default_trend_window_min")]
+    pub window_min: u64,
+    /// celsius, smaller changes over the window are steady
+    #[serde(default = "default_trend_temperature")]
+    pub temperature: f32,
+    /// percents of relative humidity
+    #[serde(default = "default_trend_humidity")]
+    pub humidity: f32,
+}
+
+impl Default for Trend {
+    fn default() -> Self {
+        Trend {
+            window_min: default_trend_window_min(),
+            temperature: default_trend_temperature(),
+            humidity: default_trend_humidity(),
+        }
+    }
+}
+
+fn default_trend_window_min() -> u64 {
+    30
+}
+
+fn default_trend_temperature() -> f32 {
+    0.5
+}
+
+fn default_trend_humidity() -> f32 {
+    3.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmoothingMethod {
+    #[default]
+    Median,
+    Mean,
+}
+
+/// Corrections of a sensor reading differently from a reference one
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Calibration {
+    #[serde(default)]
+    pub temperature: Correction,
+    #[serde(default)]
+    pub humidity: Correction,
+}
+
+/// `value * gain + offset`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Correction {
+    #[serde(default)]
+    pub offset: f32,
+    #[serde(default = "default_gain")]
+    pub gain: f32,
+}
+
+impl Default for Correction {
+    fn default() -> Self {
+        Correction { offset: 0.0, gain: default_gain() }
+    }
+}
+
+impl Correction {
+    pub fn apply(&self, value: f32) -> f32 {
+        value * self.gain + self.offset
+    }
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+fn default_sample_interval_sec() -> u64 {
+    10
+}
+
+fn default_stale_after_sec() -> u64 {
+    60
+}
+
+fn default_co2_on_display_msec() -> u64 {
+    1500
+}
+
+/// CO2 levels, the icon of the CO2 screen shows which one is reached
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Co2 {
+    pub warning: Co2Threshold,
+    pub danger: Co2Threshold,
+}
+
+impl Default for Co2 {
+    fn default() -> Self {
+        Co2 {
+            warning: Co2Threshold { ppm: 1000, alert: Co2Alert::Flash },
+            danger: Co2Threshold { ppm: 1500, alert: Co2Alert::FlashAndBuzzer },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Co2Threshold {
+    pub ppm: u16,
+    /// raised once when the level is reached
+    #[serde(default)]
+    pub alert: Co2Alert,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Co2Alert {
+    #[default]
+    None,
+    Flash,
+    Buzzer,
+    FlashAndBuzzer,
+}
+
+fn default_w1_devices() -> String {
+    "/sys/bus/w1/devices".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Probe {
+    /// e.g. `outside`, `greenhouse`
+    pub name: String,
+    /// one-wire device name, e.g. `28-0316a2794cff`, the first free discovered probe when omitted
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub calibration: Calibration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PressureUnit {
+    #[default]
+    Hpa,
+    Mmhg,
+}
+
+/// mmHg in one hPa
+const MMHG_PER_HPA: f32 = 0.750062;
+
+impl PressureUnit {
+    pub fn convert(&self, hpa: f32) -> f32 {
+        match self {
+            PressureUnit::Hpa => hpa,
+            PressureUnit::Mmhg => hpa * MMHG_PER_HPA,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    pub fn convert(&self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 1.8 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WeatherSensor {
+    /// `aht10`, `bme280` (BMP280 as well), `sht3x`, `sht4x`, `scd4x`, `mhz19`
+    #[serde(default = "default_sensor_driver")]
+    pub driver: String,
+    /// I2C bus, the serial port for `mhz19`
+    pub gpio_dev: String,
+    /// I2C address when it differs from the default one of the driver
+    #[serde(default)]
+    pub address: Option<u8>,
+    /// periodic for `scd4x` when omitted (single shot measurements take 5 s and SCD41 only has them),
+    /// single shot for the rest
+    #[serde(default)]
+    pub mode: Option<SensorMode>,
+    /// samples averaged for each measurement: 1, 2, 4, 8 or 16
+    #[serde(default = "default_oversampling")]
+    pub oversampling: u8,
+    /// IIR filter coefficient: 0 (off), 2, 4, 8 or 16
+    #[serde(default)]
+    pub filter: u8,
+    /// heats the sensor when the humidity is close to condensation, once in half an hour at most
+    #[serde(default)]
+    pub heater: bool,
+    /// member of the family handled by the `aht10` driver
+    #[serde(default)]
+    pub variant: AhtVariant,
+    #[serde(default)]
+    pub calibration: Calibration,
+}
+
+fn default_sensor_driver() -> String {
+    "aht10".to_string()
+}
+
+fn default_oversampling() -> u8 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AhtVariant {
+    #[default]
+    Aht10,
+    Aht20,
+    Aht21,
+}
+
+impl WeatherSensor {
+    pub fn mode(&self) -> SensorMode {
+        match (self.mode, self.driver.as_str()) {
+            (Some(mode), _) => mode,
+            (None, "scd4x") => SensorMode::Periodic,
+            (None, _) => SensorMode::SingleShot,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SensorMode {
+    /// a measurement on request (forced mode), the sensor sleeps in between
+    SingleShot,
+    /// the sensor measures continuously (normal mode)
+    Periodic,
+}
+
+/// Display settings applied from the given time of day until the next profile starts
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// local time as `HH:MM` or a sun event (`dawn`, `sunrise`, `sunset`, `dusk`)
+    /// with an optional offset, e.g. `sunset-00:30`
+    pub from: String,
+    pub brightness: u8,
+    /// overrides colon modes of the display and faces
+    #[serde(default)]
+    pub colon: Option<ColonMode>,
+    /// only the clock face is shown, no weather and other screens
+    #[serde(default)]
+    pub night: bool,
+}
+
+/// Place of the clock, used to calculate sunrise and sunset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Location {
+    /// degrees, north is positive
+    pub latitude: f64,
+    /// degrees, east is positive
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Screen {
+    /// sunrise and sunset times
+    Sun,
+    /// moon phase, its illumination and days to the full moon
+    Moon,
+    /// time left to the countdown events
+    Countdown,
+    /// the next event of the calendar
+    Agenda,
+    /// temperature over the graph span of the history
+    TemperatureGraph,
+    /// humidity over the graph span of the history
+    HumidityGraph,
+    PressureGraph,
+    Co2Graph,
+    /// temperature the dew falls at
+    DewPoint,
+    /// apparent temperature by the US weather service
+    HeatIndex,
+    /// apparent temperature by the Canadian weather service
+    Humidex,
+    /// grams of water in a cubic metre
+    AbsoluteHumidity,
+    /// dry, comfortable, humid or mould risk icon with the humidity
+    Comfort,
+    /// lowest and highest temperatures and humidity since the midnight
+    MinMax,
+}
+
+/// Measurements kept in memory for the graphs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct History {
+    /// minutes averaged into one value
+    #[serde(default = "default_history_resolution_min")]
+    pub resolution_min: u32,
+    #[serde(default = "default_history_length_hours")]
+    pub length_hours: u32,
+    /// time shown by the graphs, one display column is a share of it
+    #[serde(default = "default_graph_hours")]
+    pub graph_hours: u32,
+    #[serde(default)]
+    pub graph: GraphStyle,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            resolution_min: default_history_resolution_min(),
+            length_hours: default_history_length_hours(),
+            graph_hours: default_graph_hours(),
+            graph: GraphStyle::default(),
+        }
+    }
+}
+
+fn default_history_resolution_min() -> u32 {
+    5
+}
+
+fn default_history_length_hours() -> u32 {
+    24
+}
+
+fn default_graph_hours() -> u32 {
+    24
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GraphStyle {
+    #[default]
+    Bar,
+    Line,
+}
+
+/// Files of the readings, one per day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Log {
+    pub directory: String,
+    #[serde(default)]
+    pub format: LogFormat,
+    /// a day is split into more files above the size
+    #[serde(default = "default_log_max_file_kb")]
+    pub max_file_kb: u64,
+    /// days the files are kept for
+    #[serde(default = "default_log_retention_days")]
+    pub retention_days: u32,
+}
+
+fn default_log_max_file_kb() -> u64 {
+    1024
+}
+
+fn default_log_retention_days() -> u32 {
+    30
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    #[default]
+    Csv,
+    /// a JSON object per line
+    JsonLines,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Language {
+    #[default]
+    En,
+    Ru,
+}
+
+/// Events to count down to, celebrated with fireworks when they come
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Countdown {
+    pub events: Vec<Event>,
+    /// length of the fireworks
+    pub celebrate_sec: u32,
+}
+
+/// Calendar exported to an iCalendar (.ics) file, re-read when it changes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Agenda {
+    pub file: String,
+    /// events further away are not shown
+    pub lookahead_days: u32,
+    /// the display flashes the given number of minutes before an event, 0 - never
+    #[serde(default)]
+    pub flash_min: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Event {
+    pub name: String,
+    /// local `YYYY-MM-DD [HH:MM]`, or `MM-DD [HH:MM]` for an event repeated every year
+    pub date: String,
+}
+
+/// DS3231 real-time clock, used while the system time is not synchronised
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Rtc {
+    pub i2c_dev: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Timer {
+    /// how long the buzzer rings after the countdown ends
+    pub ring_msec: u64,
+    pub presets: Vec<TimerPreset>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimerPreset {
+    pub name: String,
+    pub duration_sec: u64,
+}
+
+/// Piezo buzzer connected to a PWM channel (see `/sys/class/pwm`)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Buzzer {
+    pub chip: u32,
+    pub pwm: u32,
+    pub frequency: u32,
+    /// duty in percent
+    pub duty: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Control {
+    /// unix socket which accepts commands like `timer start tea`
+    pub socket: String,
     pub gpio_dev: String,
+    pub buttons: Vec<Button>,
+}
+
+/// Push button between a GPIO line and ground, sends `command` when pressed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Button {
+    pub pin: u32,
+    pub command: String,
+}
+
+impl Default for Faces {
+    fn default() -> Self {
+        Faces {
+            rotation: vec!["digits".to_string()],
+            rotate_sec: 0,
+            colon: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Timer {
+            ring_msec: 5000,
+            presets: vec![
+                TimerPreset { name: "tea".to_string(), duration_sec: 180 },
+                TimerPreset { name: "egg".to_string(), duration_sec: 420 },
+                TimerPreset { name: "pizza".to_string(), duration_sec: 720 },
+            ],
+        }
+    }
+}
+
+impl Default for Countdown {
+    fn default() -> Self {
+        Countdown { events: vec![], celebrate_sec: 10 }
+    }
+}
+
+impl Default for Control {
+    fn default() -> Self {
+        Control {
+            socket: "/tmp/clock.sock".to_string(),
+            gpio_dev: "/dev/gpiochip0".to_string(),
+            buttons: vec![],
+        }
+    }
 }
 
 impl Config {
@@ -46,13 +718,73 @@ impl Config {
                 number_of_matrices: 4,
                 brightness: 0x0F, // max
                 slim: false,
+                scroll_msec: default_scroll_msec(),
+                screen_msec: default_screen_msec(),
+                colon: ColonMode::Blink,
             },
+            faces: Faces::default(),
             weather: Weather {
                 display_interval_sec: 20,
                 humidity_on_display_msec: 1000,
                 temperature_on_display_msec: 1500,
-                sensor: WeatherSensor { gpio_dev: "/dev/gpiochip0".to_string() },
+                unit: TemperatureUnit::Celsius,
+                pressure_on_display_msec: default_pressure_on_display_msec(),
+                pressure_unit: PressureUnit::Hpa,
+                co2_on_display_msec: default_co2_on_display_msec(),
+                co2: Co2::default(),
+                sample_interval_sec: default_sample_interval_sec(),
+                stale_after_sec: default_stale_after_sec(),
+                smoothing: Smoothing::default(),
+                trend: Trend::default(),
+                compensation: None,
+                sensor: WeatherSensor {
+                    driver: default_sensor_driver(),
+                    gpio_dev: "/dev/gpiochip0".to_string(),
+                    address: None,
+                    mode: None,
+                    oversampling: default_oversampling(),
+                    filter: 0,
+                    heater: false,
+                    variant: AhtVariant::Aht10,
+                    calibration: Calibration::default(),
+                },
+                co2_sensor: None,
+                probes: Vec::new(),
+                w1_devices: default_w1_devices(),
+            },
+            timer: Timer::default(),
+            buzzer: None,
+            control: Control::default(),
+            rtc: None,
+            profiles: vec![
+                Profile {
+                    name: "day".to_string(),
+                    from: "07:00".to_string(),
+                    brightness: 0x0F,
+                    colon: None,
+                    night: false,
+                },
+                Profile {
+                    name: "night".to_string(),
+                    from: "22:00".to_string(),
+                    brightness: 0x01,
+                    colon: Some(ColonMode::Steady),
+                    night: true,
+                },
+            ],
+            location: None,
+            screens: vec![],
+            history: History::default(),
+            log: None,
+            countdown: Countdown {
+                events: vec![Event {
+                    name: "New Year".to_string(),
+                    date: "01-01".to_string(),
+                }],
+                ..Countdown::default()
             },
+            agenda: None,
+            language: Language::En,
         }
     }
 
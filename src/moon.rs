@@ -0,0 +1,146 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+/// Mean daily motion of the moon relative to the sun, degrees
+const ELONGATION_RATE: f64 = 12.190749;
+
+/// Moon phase at a moment
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Phase {
+    /// angle between the moon and the sun along the ecliptic, 0 - new moon, 180 - full moon
+    pub elongation: f64,
+    /// time of the next full moon
+    pub next_full: DateTime<Utc>,
+}
+
+impl Phase {
+    /// Lit part of the disk, 0.0..=1.0
+    pub fn illumination(&self) -> f64 {
+        (1.0 - self.elongation.to_radians().cos()) / 2.0
+    }
+
+    pub fn waxing(&self) -> bool {
+        self.elongation < 180.0
+    }
+
+    /// 8x8 picture of the phase as seen from the northern hemisphere, columns with bit 0 on the top row
+    pub fn icon(&self) -> [u8; 8] {
+        const RADIUS: f64 = 3.7;
+        let terminator = self.elongation.to_radians().cos();
+        let mut icon = [0; 8];
+        for (x, column) in icon.iter_mut().enumerate() {
+            for y in 0..8 {
+                let (dx, dy) = (x as f64 - 3.5, y as f64 - 3.5);
+                if dx * dx + dy * dy > RADIUS * RADIUS {
+                    continue;
+                }
+                // half width of the disk on the row, the terminator is an ellipse through its poles
+                let half = (RADIUS * RADIUS - dy * dy).sqrt();
+                let lit = if self.waxing() {
+                    dx > half * terminator
+                } else {
+                    dx < -half * terminator
+                };
+                *column |= (lit as u8) << y;
+            }
+        }
+        icon
+    }
+}
+
+/// Moon phase at the time.
+/// Low precision lunar and solar longitudes, the full and new moons are within an hour of the published ones.
+pub(crate) fn phase(time: &DateTime<Utc>) -> Phase {
+    let elongation = elongation(time);
+
+    // the moon speed varies, a few corrections land on the full moon
+    let mut next_full = *time + days((180.0 - elongation).rem_euclid(360.0) / ELONGATION_RATE);
+    for _ in 0..3 {
+        let error = (180.0 - self::elongation(&next_full) + 180.0).rem_euclid(360.0) - 180.0;
+        next_full = next_full + days(error / ELONGATION_RATE);
+    }
+
+    Phase { elongation, next_full }
+}
+
+fn days(days: f64) -> Duration {
+    Duration::seconds((days * 86400.0).round() as i64)
+}
+
+/// Elongation of the moon in degrees, 0..360
+fn elongation(time: &DateTime<Utc>) -> f64 {
+    let days = (*time - Utc.ymd(2000, 1, 1).and_hms(12, 0, 0)).num_seconds() as f64 / 86400.0;
+    let sin = |degrees: f64| degrees.to_radians().sin();
+
+    // mean longitude, mean anomaly and mean elongation of the moon, mean anomaly of the sun
+    let moon_longitude = 218.316 + 13.176396 * days;
+    let moon_anomaly = 134.963 + 13.064993 * days;
+    let mean_elongation = 297.850 + ELONGATION_RATE * days;
+    let sun_anomaly = 357.529 + 0.98560028 * days;
+
+    let moon = moon_longitude + 6.289 * sin(moon_anomaly) // equation of the center
+        + 1.274 * sin(2.0 * mean_elongation - moon_anomaly) // evection
+        + 0.658 * sin(2.0 * mean_elongation) // variation
+        - 0.186 * sin(sun_anomaly) // annual equation
+        - 0.059 * sin(2.0 * mean_elongation - 2.0 * moon_anomaly)
+        - 0.057 * sin(2.0 * mean_elongation - moon_anomaly - sun_anomaly);
+    let sun = 280.460 + 0.9856474 * days + 1.915 * sin(sun_anomaly) + 0.020 * sin(2.0 * sun_anomaly);
+
+    (moon - sun).rem_euclid(360.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    /// Full moons published by NASA
+    const FULL_MOONS: [&str; 4] = [
+        "2021-01-28T19:16:00Z",
+        "2021-05-26T11:14:00Z",
+        "2022-01-17T23:48:00Z",
+        "2023-08-31T01:36:00Z",
+    ];
+
+    /// New moons published by NASA
+    const NEW_MOONS: [&str; 4] = [
+        "2021-01-13T05:00:00Z",
+        "2022-01-02T18:33:00Z",
+        "2023-08-16T09:38:00Z",
+        "2024-04-08T18:21:00Z",
+    ];
+
+    #[test]
+    fn finds_full_moons_within_an_hour() {
+        for full in FULL_MOONS.iter().map(|time| utc(time)) {
+            for before in [1, 7, 20] {
+                let next_full = phase(&(full - Duration::days(before))).next_full;
+                let minutes = (next_full - full).num_minutes().abs();
+                assert!(
+                    minutes <= 60,
+                    "{} days before {}: {} is {} minutes off",
+                    before,
+                    full,
+                    next_full,
+                    minutes
+                );
+            }
+            let phase = phase(&full);
+            assert!(phase.illumination() > 0.999, "{} is lit {}", full, phase.illumination());
+        }
+    }
+
+    #[test]
+    fn new_moons_within_an_hour() {
+        for new in NEW_MOONS.iter().map(|time| utc(time)) {
+            // the moon passes the sun within the hour
+            let before = phase(&(new - Duration::hours(1)));
+            let after = phase(&(new + Duration::hours(1)));
+            assert!(before.elongation > 350.0 && !before.waxing(), "{} {:?}", new, before);
+            assert!(after.elongation < 10.0 && after.waxing(), "{} {:?}", new, after);
+            assert!(phase(&new).illumination() < 0.001);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime};
+
+use crate::{
+    error::Error,
+    model::{self, ColonMode, Location},
+    sun::{self, SunEvent},
+};
+
+/// When a profile starts: a fixed time of day or a sun event shifted by the offset
+#[derive(Debug, Clone, Copy)]
+enum Start {
+    Time(NaiveTime),
+    Sun(SunEvent, Duration),
+}
+
+impl Start {
+    /// Parses `HH:MM` or `sunset`, `dawn+00:30`, `dusk-01:00` and so on
+    fn parse(from: &str) -> Option<Start> {
+        if let Ok(time) = NaiveTime::parse_from_str(from, "%H:%M") {
+            return Some(Start::Time(time));
+        }
+        let (name, offset) = match from.find(['+', '-']) {
+            Some(i) => {
+                let offset = NaiveTime::parse_from_str(&from[i + 1..], "%H:%M").ok()? - NaiveTime::from_hms(0, 0, 0);
+                (&from[..i], if &from[i..=i] == "-" { -offset } else { offset })
+            }
+            None => (from, Duration::zero()),
+        };
+        let event = match name.trim() {
+            "dawn" => SunEvent::Dawn,
+            "sunrise" => SunEvent::Sunrise,
+            "sunset" => SunEvent::Sunset,
+            "dusk" => SunEvent::Dusk,
+            _ => return None,
+        };
+        Some(Start::Sun(event, offset))
+    }
+}
+
+/// Brightness profiles switched by the time of day or by the sun
+pub(crate) struct Profiles<'a> {
+    profiles: Vec<(Start, &'a model::Profile)>,
+    location: Option<&'a Location>,
+    default_brightness: u8,
+    /// start times of the profiles resolved for the day, sorted
+    schedule: Option<(NaiveDate, Vec<(NaiveTime, usize)>)>,
+    active: Option<usize>,
+    started: bool,
+}
+
+impl<'a> Profiles<'a> {
+    pub fn new(
+        config: &'a [model::Profile],
+        location: Option<&'a Location>,
+        default_brightness: u8,
+    ) -> Result<Self, Error> {
+        let profiles = config
+            .iter()
+            .map(|profile| match Start::parse(&profile.from) {
+                Some(Start::Sun(..)) if location.is_none() => Err(Error::LocationError {}),
+                Some(start) => Ok((start, profile)),
+                None => Err(Error::ProfileTimeError { from: profile.from.clone() }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Profiles {
+            profiles,
+            location,
+            default_brightness,
+            schedule: None,
+            active: None,
+            started: false,
+        })
+    }
+
+    /// Switches to the profile active at the time, returns the brightness to set when it has been changed
+    pub fn update(&mut self, time: &DateTime<Local>) -> Option<u8> {
+        let now = time.time();
+        let schedule = self.schedule(time.date().naive_local());
+        // the latest profile started today, or the last one started yesterday
+        let active = schedule
+            .iter()
+            .rev()
+            .find(|(from, _)| *from <= now)
+            .or_else(|| schedule.last())
+            .map(|(_, i)| *i);
+        if self.started && active == self.active {
+            return None;
+        }
+        self.started = true;
+        self.active = active;
+
+        match self.profile() {
+            Some(profile) => {
+                println!("Profile `{}` is active", profile.name);
+                Some(profile.brightness)
+            }
+            None => Some(self.default_brightness),
+        }
+    }
+
+    /// Colon mode of the active profile, if it sets one
+    pub fn colon(&self) -> Option<ColonMode> {
+        self.profile().and_then(|profile| profile.colon)
+    }
+
+    /// `true` when the active profile shows nothing but the clock face
+    pub fn night(&self) -> bool {
+        self.profile().is_some_and(|profile| profile.night)
+    }
+
+    fn profile(&self) -> Option<&'a model::Profile> {
+        self.active.map(|i| self.profiles[i].1)
+    }
+
+    /// Start times for the date, sun events are calculated once a day.
+    /// Profiles whose sun event does not happen that day (polar day or night) are skipped.
+    fn schedule(&mut self, date: NaiveDate) -> &[(NaiveTime, usize)] {
+        if !matches!(&self.schedule, Some((day, _)) if *day == date) {
+            let mut schedule = self
+                .profiles
+                .iter()
+                .enumerate()
+                .filter_map(|(i, (start, _))| {
+                    let from = match *start {
+                        Start::Time(time) => time,
+                        Start::Sun(event, offset) => {
+                            let time = sun::event(date, self.location?, event)?;
+                            (time.with_timezone(&Local) + offset).time()
+                        }
+                    };
+                    Some((from, i))
+                })
+                .collect::<Vec<_>>();
+            schedule.sort();
+            self.schedule = Some((date, schedule));
+        }
+        self.schedule.as_ref().map_or(&[], |(_, schedule)| schedule)
+    }
+}
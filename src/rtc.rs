@@ -0,0 +1,120 @@
+use chrono::{DateTime, NaiveTime, Timelike, Utc};
+use linux_embedded_hal::I2cdev;
+use snafu::ResultExt;
+use std::sync::Arc;
+
+use crate::{
+    ds3231::{Alarm, AlarmMatch, DS3231},
+    error::{self, Error},
+    model::{self, Config},
+    time_source::{self, RtcFallbackClock, SystemClock, TimeSource},
+};
+
+pub(crate) fn open(config: &model::Rtc) -> Result<DS3231<I2cdev>, Error> {
+    Ok(DS3231 {
+        i2c: I2cdev::new(&config.i2c_dev).context(error::I2CSnafu)?,
+    })
+}
+
+/// System clock backed by the RTC when it is configured and keeps valid time.
+/// The clock starts with the system time alone when the RTC cannot be read.
+pub(crate) fn time_source(config: &Option<model::Rtc>) -> Arc<dyn TimeSource> {
+    let config = match config {
+        Some(config) => config,
+        None => return Arc::new(SystemClock),
+    };
+
+    match read_valid_time(config) {
+        Ok(Some(time)) => {
+            if !time_source::system_synchronized() {
+                println!("System time is not synchronised, RTC time {} is used", time);
+            }
+            Arc::new(RtcFallbackClock::new(time))
+        }
+        Ok(None) => {
+            println!("RTC has lost power, its time is ignored");
+            Arc::new(SystemClock)
+        }
+        Err(e) => {
+            eprintln!("RTC time is ignored: {}", e);
+            Arc::new(SystemClock)
+        }
+    }
+}
+
+/// RTC time, `None` when it is not valid since the power was lost
+fn read_valid_time(config: &model::Rtc) -> Result<Option<DateTime<Utc>>, Error> {
+    let mut rtc = open(config)?;
+    if rtc.lost_power().context(error::RtcSnafu)? {
+        return Ok(None);
+    }
+    Ok(Some(DateTime::<Utc>::from_utc(
+        rtc.datetime().context(error::RtcSnafu)?,
+        Utc,
+    )))
+}
+
+/// `rtc` subcommand: shows RTC state, syncs the time between RTC and system, sets aging offset and alarms
+pub(crate) fn command(config: &Config, opts: &clap::ArgMatches) -> Result<(), Error> {
+    let mut rtc = open(config.rtc.as_ref().ok_or(Error::RtcConfigError {})?)?;
+
+    if opts.is_present("to-system") {
+        let time = DateTime::<Utc>::from_utc(rtc.datetime().context(error::RtcSnafu)?, Utc);
+        time_source::set_system_time(time).context(error::SystemTimeSnafu)?;
+        println!("System time is set to {}", time);
+    }
+    if opts.is_present("from-system") {
+        let time = Utc::now().naive_utc();
+        rtc.set_datetime(&time).context(error::RtcSnafu)?;
+        println!("RTC time is set to {}", time);
+    }
+    if let Some(offset) = opts.value_of("aging") {
+        let offset = offset
+            .parse()
+            .map_err(|_| Error::RtcOptionError { value: offset.to_string() })?;
+        rtc.set_aging_offset(offset).context(error::RtcSnafu)?;
+    }
+    if let Some(time) = opts.value_of("alarm1") {
+        rtc.set_alarm1(&daily_alarm(time)?).context(error::RtcSnafu)?;
+    }
+    if let Some(time) = opts.value_of("alarm2") {
+        rtc.set_alarm2(&daily_alarm(time)?).context(error::RtcSnafu)?;
+    }
+    if opts.is_present("alarm1") || opts.is_present("alarm2") || opts.is_present("clear-alarms") {
+        // only the alarms given are enabled, the other one stays as it is
+        let enable = |alarm| match opts.is_present("clear-alarms") {
+            true => Some(false),
+            false => opts.is_present(alarm).then_some(true),
+        };
+        rtc.enable_alarms(enable("alarm1"), enable("alarm2"))
+            .context(error::RtcSnafu)?;
+        rtc.take_alarms().context(error::RtcSnafu)?;
+    }
+
+    let lost_power = rtc.lost_power().context(error::RtcSnafu)?;
+    match rtc.datetime() {
+        Ok(time) if !lost_power => println!("RTC time:     {} UTC", time),
+        Ok(time) => println!("RTC time:     {} UTC (not valid, power was lost)", time),
+        Err(e) => println!("RTC time:     {}", e),
+    }
+    println!("Temperature:  {:.2}°C", rtc.temperature().context(error::RtcSnafu)?);
+    println!("Aging offset: {}", rtc.aging_offset().context(error::RtcSnafu)?);
+    println!("Alarm 1:      {:?}", rtc.alarm1().context(error::RtcSnafu)?);
+    println!("Alarm 2:      {:?}", rtc.alarm2().context(error::RtcSnafu)?);
+
+    Ok(())
+}
+
+/// Alarm firing every day at `HH:MM[:SS]` UTC
+fn daily_alarm(time: &str) -> Result<Alarm, Error> {
+    let parsed = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .map_err(|_| Error::RtcOptionError { value: time.to_string() })?;
+    Ok(Alarm {
+        matching: AlarmMatch::Hour,
+        second: parsed.second() as u8,
+        minute: parsed.minute() as u8,
+        hour: parsed.hour() as u8,
+        day: 1,
+    })
+}
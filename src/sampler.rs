@@ -0,0 +1,320 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
+
+use crate::{
+    compensation::{Compensation, HeatLoad},
+    display::DutyMeter,
+    ds18b20::Ds18b20,
+    error::Error,
+    filter::Filter,
+    history::History,
+    logger::{self, Logger, Record},
+    model,
+    sensor::{Capabilities, Measurement, Sensor},
+    time_source::TimeSource,
+};
+
+/// Measurements are repeated when the data is corrupted on the way from the sensor
+const MEASURE_ATTEMPTS: usize = 3;
+const MEASURE_POLL_MSEC: u64 = 10;
+const MEASURE_TIMEOUT_MSEC: u64 = 1000;
+
+/// Latest measurement of a sensor or a probe
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Reading {
+    /// calibrated and smoothed
+    pub measurement: Option<Measurement>,
+    /// as the sensor has measured it
+    pub raw: Option<Measurement>,
+    /// heat sources the measurement is compensated for
+    pub load: Option<HeatLoad>,
+    /// when the measurement was taken
+    pub time: Option<Instant>,
+    /// the last attempt has failed, the measurement is the one before it
+    pub failed: bool,
+}
+
+impl Reading {
+    /// The measurement unless it is too old or the sensor has failed since
+    pub fn fresh(&self, max_age: Duration) -> Option<&Measurement> {
+        let stale = self.time.is_none_or(|time| time.elapsed() > max_age);
+        self.measurement.as_ref().filter(|_| !self.failed && !stale)
+    }
+
+    /// `true` when there is a new measurement
+    fn update(&mut self, name: &str, result: Result<Measurement, Error>, filter: &mut Filter) -> bool {
+        match result.map(|raw| (filter.apply(&raw), raw)) {
+            Ok((Some(measurement), raw)) => {
+                // the first measurement and the one after failures are reported
+                let report = self.failed || self.time.is_none();
+                *self = Reading {
+                    measurement: Some(measurement),
+                    raw: Some(raw),
+                    load: filter.load(),
+                    time: Some(Instant::now()),
+                    failed: false,
+                };
+                if report {
+                    println!("`{}`: {}", name, self);
+                }
+                true
+            }
+            // the previous measurement stays until it gets stale
+            Ok((None, raw)) => {
+                eprintln!("`{}` measurement {} is rejected as a jump", name, raw);
+                false
+            }
+            // nothing is measured, the previous measurement stays too
+            Err(Error::SensorCoolingError {}) => false,
+            Err(e) => {
+                // reported once, the sensor is tried again in the next period
+                if !self.failed {
+                    eprintln!("`{}` measurement has failed: {}", name, e);
+                }
+                self.failed = true;
+                false
+            }
+        }
+    }
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.measurement, &self.raw) {
+            (Some(measurement), Some(raw)) => {
+                write!(f, "{} (raw {}", measurement, raw)?;
+                if let Some(load) = &self.load {
+                    write!(f, ", {}", load)?;
+                }
+                write!(f, ")")
+            }
+            _ => write!(f, "no measurement"),
+        }
+    }
+}
+
+/// Readings of the sensor and the probes in the order of the config
+#[derive(Debug, Clone)]
+pub(crate) struct Readings {
+    pub sensor: Reading,
+    pub probes: Vec<Reading>,
+}
+
+impl Readings {
+    fn new(probes: usize) -> Self {
+        Readings {
+            sensor: Reading::default(),
+            probes: vec![Reading::default(); probes],
+        }
+    }
+}
+
+/// Measures the sensor and the probes in its own thread, the screens get the latest readings
+pub(crate) struct Sampler {
+    readings: watch::Receiver<Readings>,
+    history: Arc<Mutex<History>>,
+    capabilities: Capabilities,
+    max_age: Duration,
+}
+
+impl Sampler {
+    /// The sensor is `None` when it cannot be opened, it is shown as unknown then
+    pub fn spawn(
+        mut sensor: Option<Box<dyn Sensor>>,
+        mut probes: Vec<Ds18b20>,
+        config: &model::Config,
+        duty: DutyMeter,
+        time_source: Arc<dyn TimeSource>,
+    ) -> Self {
+        let history = Arc::new(Mutex::new(restore(config, time_source.as_ref())));
+        let mut logger = config.log.as_ref().map(Logger::new);
+        let config = &config.weather;
+        let capabilities = sensor
+            .as_ref()
+            .map_or(Capabilities::TEMPERATURE, |sensor| sensor.capabilities());
+        let driver = config.sensor.driver.clone();
+        let period = Duration::from_secs(config.sample_interval_sec.max(1));
+        let (sender, receiver) = watch::channel(Readings::new(probes.len()));
+        // without the coefficients the heat load is only logged, `calibrate-sensor` fits them to the log
+        let compensation = Compensation::new(&config.compensation.clone().unwrap_or_default(), duty);
+        let mut sensor_filter = Filter::new(&config.sensor.calibration, &config.smoothing, Some(compensation));
+        let mut probe_filters = config
+            .probes
+            .iter()
+            .map(|probe| Filter::new(&probe.calibration, &config.smoothing, None))
+            .collect::<Vec<_>>();
+
+        let recorded = history.clone();
+        // the drivers block on I2C, serial and sysfs, the runtime has a single worker for the display
+        thread::spawn(move || {
+            let mut readings = Readings::new(probes.len());
+            let mut next = Instant::now();
+            let mut log_failed = false;
+            loop {
+                // missed periods are skipped
+                let now = Instant::now();
+                if next > now {
+                    thread::sleep(next - now);
+                }
+                next = next.max(now) + period;
+
+                let now = time_source.now();
+                let mut records = Vec::new();
+                if let Some(sensor) = &mut sensor {
+                    let result = measure(sensor.as_mut());
+                    if readings.sensor.update(&driver, result, &mut sensor_filter) {
+                        if let Some(measurement) = &readings.sensor.measurement {
+                            recorded.lock().unwrap().push(now.timestamp(), measurement);
+                        }
+                        records.extend(Record::new(now.timestamp(), &driver, &readings.sensor));
+                    }
+                }
+                for ((reading, probe), filter) in readings.probes.iter_mut().zip(&mut probes).zip(&mut probe_filters) {
+                    let result = measure(probe);
+                    if reading.update(&probe.name, result, filter) {
+                        records.extend(Record::new(now.timestamp(), &probe.name, reading));
+                    }
+                }
+                if let Some(logger) = &mut logger {
+                    // reported once, the log is tried again with the next readings
+                    match logger.write(&now, &records) {
+                        Ok(()) => log_failed = false,
+                        Err(e) if !log_failed => {
+                            eprintln!("{}", e);
+                            log_failed = true;
+                        }
+                        Err(_) => {}
+                    }
+                }
+                if sender.send(readings.clone()).is_err() {
+                    // nobody shows the readings anymore
+                    break;
+                }
+            }
+        });
+
+        Sampler {
+            readings: receiver,
+            history,
+            capabilities,
+            max_age: Duration::from_secs(config.stale_after_sec),
+        }
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// How old a measurement may be to be shown
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Measurements of the sensor, recorded as they come
+    pub fn history(&self) -> Arc<Mutex<History>> {
+        self.history.clone()
+    }
+
+    pub fn latest(&self) -> Readings {
+        self.readings.borrow().clone()
+    }
+}
+
+/// History of the sensor filled from the log, empty without it
+fn restore(config: &model::Config, time_source: &dyn TimeSource) -> History {
+    let mut history = History::new(&config.history);
+    if let Some(log) = &config.log {
+        let now = time_source.now();
+        let from = now - chrono::Duration::hours(config.history.length_hours as i64);
+        match logger::read(log, &from, &now) {
+            Ok(records) => records
+                .iter()
+                .filter(|record| record.sensor == config.weather.sensor.driver)
+                .for_each(|record| history.push(record.time, &record.measurement())),
+            Err(e) => eprintln!("History is not restored: {}", e),
+        }
+    }
+    history
+}
+
+fn measure<S: Sensor + ?Sized>(sensor: &mut S) -> Result<Measurement, Error> {
+    let mut attempt = 1;
+    loop {
+        match sample(sensor) {
+            Err(Error::SensorCrcError {}) if attempt < MEASURE_ATTEMPTS => attempt += 1,
+            result => return result,
+        }
+    }
+}
+
+/// Starts a measurement and polls for its result
+fn sample<S: Sensor + ?Sized>(sensor: &mut S) -> Result<Measurement, Error> {
+    thread::sleep(sensor.start()?);
+    for _ in 0..MEASURE_TIMEOUT_MSEC / MEASURE_POLL_MSEC {
+        if let Some(measurement) = sensor.poll()? {
+            return Ok(measurement);
+        }
+        thread::sleep(Duration::from_millis(MEASURE_POLL_MSEC));
+    }
+    Err(Error::SensorTimeoutError {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_source::SystemClock;
+    use std::thread::ThreadId;
+
+    /// Sensor telling the thread it is measured on
+    struct Fake(Arc<Mutex<Option<ThreadId>>>);
+
+    impl Sensor for Fake {
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::TEMPERATURE | Capabilities::HUMIDITY
+        }
+
+        fn measure(&mut self) -> Result<Measurement, Error> {
+            *self.0.lock().unwrap() = Some(thread::current().id());
+            Ok(Measurement {
+                temperature: 21.5,
+                humidity: Some(45.0),
+                pressure: None,
+                co2: None,
+            })
+        }
+    }
+
+    fn spawn(sensor: Option<Box<dyn Sensor>>) -> Sampler {
+        let config = model::Config::new();
+        Sampler::spawn(sensor, vec![], &config, DutyMeter::default(), Arc::new(SystemClock))
+    }
+
+    #[test]
+    fn measures_on_own_thread() {
+        let measured_on = Arc::new(Mutex::new(None));
+        let sampler = spawn(Some(Box::new(Fake(measured_on.clone()))));
+        for _ in 0..100 {
+            if sampler.latest().sensor.measurement.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let measurement = sampler.latest().sensor.fresh(sampler.max_age()).copied().unwrap();
+        assert_eq!((measurement.temperature, measurement.humidity), (21.5, Some(45.0)));
+        let measured_on = measured_on.lock().unwrap().unwrap();
+        assert_ne!(measured_on, thread::current().id());
+    }
+
+    #[test]
+    fn goes_on_without_sensor() {
+        let sampler = spawn(None);
+        assert_eq!(sampler.capabilities(), Capabilities::TEMPERATURE);
+        thread::sleep(Duration::from_millis(50));
+        assert!(sampler.latest().sensor.fresh(sampler.max_age()).is_none());
+    }
+}
@@ -0,0 +1,20 @@
+use snafu::Snafu;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum Error<I2CError>
+where
+    I2CError: 'static + std::error::Error,
+{
+    #[snafu(display("SCD4x read error"))]
+    ReadError { source: I2CError },
+
+    #[snafu(display("SCD4x write error"))]
+    WriteError { source: I2CError },
+
+    #[snafu(display("SCD4x data CRC mismatch"))]
+    CrcError {},
+
+    #[snafu(display("SCD4x measurement timeout"))]
+    TimeoutError {},
+}
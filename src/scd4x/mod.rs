@@ -0,0 +1,112 @@
+pub mod error;
+
+use embedded_hal::blocking::i2c::{Read, Write};
+use error::Error;
+use snafu::ResultExt;
+
+use crate::crc::crc8;
+
+pub const I2C_ADDRESS: u8 = 0x62; // SCD40 and SCD41 have the same static address
+
+const START_PERIODIC: u16 = 0x21B1; // a measurement every 5 seconds
+const STOP_PERIODIC: u16 = 0x3F86;
+const MEASURE_SINGLE_SHOT: u16 = 0x219D; // SCD41 only
+const READ_MEASUREMENT: u16 = 0xEC05;
+const DATA_READY: u16 = 0xE4B8;
+const REINIT: u16 = 0x3646;
+
+const STOP_MS: u32 = 500;
+const REINIT_MS: u32 = 20;
+const COMMAND_MS: u32 = 1;
+const SINGLE_SHOT_MS: u32 = 5000;
+const DATA_READY_POLL_MS: u32 = 100;
+const DATA_READY_TIMEOUT_MS: u32 = 6000;
+
+/// Sensirion SCD40/SCD41 photoacoustic CO2 sensor with temperature and humidity
+pub struct Scd4x<I2C> {
+    i2c: I2C,
+    delay_ms: fn(u32) -> (),
+    periodic: bool,
+}
+
+impl<I2C, I2CError> Scd4x<I2C>
+where
+    I2CError: std::error::Error,
+    I2C: Read<Error = I2CError> + Write<Error = I2CError>,
+{
+    pub fn new(i2c: I2C, delay_ms: fn(u32) -> ()) -> Self {
+        Scd4x { i2c, delay_ms, periodic: false }
+    }
+
+    /// Stops measurements left running since the last start, reloads the settings
+    /// and starts periodic measurements when they are requested.
+    /// Single shot measurements are supported by SCD41 only and take 5 seconds.
+    pub fn init(&mut self, periodic: bool) -> Result<(), Error<I2CError>> {
+        // the sensor ignores other commands while measuring periodically
+        self.command(STOP_PERIODIC)?;
+        (self.delay_ms)(STOP_MS);
+        self.command(REINIT)?;
+        (self.delay_ms)(REINIT_MS);
+
+        if periodic {
+            self.command(START_PERIODIC)?;
+        }
+        self.periodic = periodic;
+        Ok(())
+    }
+
+    /// Measures CO2 (ppm), temperature (celsius) and relative humidity (%),
+    /// waits for the first periodic measurement after the start
+    pub fn measure(&mut self) -> Result<(u16, f32, f32), Error<I2CError>> {
+        if self.periodic {
+            self.wait()?;
+        } else {
+            self.command(MEASURE_SINGLE_SHOT)?;
+            (self.delay_ms)(SINGLE_SHOT_MS);
+        }
+
+        let mut buf = [0; 9];
+        self.read(READ_MEASUREMENT, &mut buf)?;
+        let co2 = word(&buf[0..3])?;
+        let temperature = -45.0 + 175.0 * word(&buf[3..6])? as f32 / 65535.0;
+        let humidity = 100.0 * word(&buf[6..9])? as f32 / 65535.0;
+        Ok((co2, temperature, humidity.clamp(0.0, 100.0)))
+    }
+
+    /// Waits until a periodic measurement is ready
+    fn wait(&mut self) -> Result<(), Error<I2CError>> {
+        let mut buf = [0; 3];
+        for _ in 0..DATA_READY_TIMEOUT_MS / DATA_READY_POLL_MS {
+            self.read(DATA_READY, &mut buf)?;
+            // the lowest 11 bits are zero while there is no new data
+            if word(&buf)? & 0x07FF != 0 {
+                return Ok(());
+            }
+            (self.delay_ms)(DATA_READY_POLL_MS);
+        }
+        Err(Error::TimeoutError {})
+    }
+
+    fn read(&mut self, command: u16, buf: &mut [u8]) -> Result<(), Error<I2CError>> {
+        self.command(command)?;
+        (self.delay_ms)(COMMAND_MS);
+        self.i2c.read(I2C_ADDRESS, buf).context(error::ReadSnafu)
+    }
+
+    fn command(&mut self, command: u16) -> Result<(), Error<I2CError>> {
+        self.i2c
+            .write(I2C_ADDRESS, &command.to_be_bytes())
+            .context(error::WriteSnafu)
+    }
+}
+
+/// 16-bit word followed by its CRC
+fn word<E>(data: &[u8]) -> Result<u16, Error<E>>
+where
+    E: 'static + std::error::Error,
+{
+    if crc8(&data[0..2]) != data[2] {
+        return Err(Error::CrcError {});
+    }
+    Ok(u16::from_be_bytes([data[0], data[1]]))
+}
@@ -0,0 +1,245 @@
+use chrono::{DateTime, Duration, DurationRound, Local};
+use std::sync::Arc;
+use tokio::time::{self, Instant};
+
+use crate::time_source::TimeSource;
+
+/// Ticks twice a second: on the whole second and on the half
+const PERIOD_MS: i64 = 500;
+/// Wake-ups this close to the boundary are considered to be on it
+const TOLERANCE_MS: i64 = 50;
+/// Difference between wall and monotonic clocks treated as a clock step (NTP, suspend, manual change)
+const JUMP_THRESHOLD_MS: i64 = 1000;
+
+pub(crate) struct Tick {
+    /// wall-clock time of the boundary the tick belongs to
+    pub time: DateTime<Local>,
+    /// `true` for the first half of a second, i.e. when blinking elements are lit
+    pub first_half: bool,
+    /// system clock has been stepped since the previous tick
+    pub jumped: bool,
+}
+
+/// Wakes up on exact second/half-second boundaries of the wall clock.
+/// Sleeps are measured with the monotonic clock, so each of them is recalculated from the wall time,
+/// ticks which were missed due to long drawing are skipped instead of being caught up.
+/// Simulated (scaled or frozen) time is not aligned, the scheduler just ticks every real half-second then.
+pub(crate) struct Scheduler {
+    source: Arc<dyn TimeSource>,
+    last: Option<(Instant, DateTime<Local>)>,
+    /// boundary of the previous tick, never returned twice
+    previous: Option<DateTime<Local>>,
+    simulated_half: bool,
+}
+
+impl Scheduler {
+    pub fn new(source: Arc<dyn TimeSource>) -> Self {
+        Scheduler {
+            source,
+            last: None,
+            previous: None,
+            simulated_half: false,
+        }
+    }
+
+    pub async fn tick(&mut self) -> Tick {
+        let period = Duration::milliseconds(PERIOD_MS);
+        if (self.source.scale() - 1.0).abs() > f64::EPSILON {
+            time::sleep(period.to_std().unwrap_or_default()).await;
+            // blinks in turn, whatever the simulated time is
+            self.simulated_half = !self.simulated_half;
+            return Tick {
+                time: self.source.now(),
+                first_half: self.simulated_half,
+                jumped: false,
+            };
+        }
+
+        let mut jumped = false;
+        loop {
+            let now = self.source.now();
+            let instant = Instant::now();
+            jumped |= self.detect_jump(instant, now);
+
+            // next boundary, a boundary which has just passed counts as the current one
+            let nearest = now.duration_round(period).unwrap_or(now);
+            let mut target = if (nearest - now).num_milliseconds().abs() <= TOLERANCE_MS {
+                nearest
+            } else {
+                now.duration_trunc(period).unwrap_or(now) + period
+            };
+            match self.previous {
+                Some(previous) if !jumped && target <= previous => target = previous + period,
+                _ => {}
+            }
+
+            if let Ok(wait) = (target - now).to_std() {
+                time::sleep_until(instant + wait).await;
+            }
+
+            // the wall clock could be stepped while sleeping, then align again
+            let woke = self.source.now();
+            self.last = Some((Instant::now(), woke));
+            if (woke - target).num_milliseconds().abs() > JUMP_THRESHOLD_MS {
+                println!("System clock jumped while waiting for the tick, resynchronizing");
+                jumped = true;
+                continue;
+            }
+
+            self.previous = Some(target);
+            let first_half = target.timestamp_subsec_millis() < PERIOD_MS as u32;
+            return Tick { time: target, first_half, jumped };
+        }
+    }
+
+    /// Compares the wall-clock time passed since the previous tick with the monotonic one
+    fn detect_jump(&self, instant: Instant, now: DateTime<Local>) -> bool {
+        let (last_instant, last_time) = match self.last {
+            Some(last) => last,
+            None => return false,
+        };
+        let monotonic = Duration::from_std(instant - last_instant).unwrap_or_else(|_| Duration::zero());
+        let drift = (now - last_time) - monotonic;
+        if drift.num_milliseconds().abs() > JUMP_THRESHOLD_MS {
+            println!(
+                "System clock jumped by {} ms, resynchronizing",
+                drift.num_milliseconds()
+            );
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_source::{self, SimulatedClock};
+    use chrono::TimeZone;
+    use std::sync::Mutex;
+
+    /// Wall clock following the paused tokio time, it can be stepped like the system one
+    struct TestClock {
+        start: DateTime<Local>,
+        started: Instant,
+        step: Mutex<Duration>,
+    }
+
+    impl TestClock {
+        fn new(start: DateTime<Local>) -> Arc<Self> {
+            Arc::new(TestClock {
+                start,
+                started: Instant::now(),
+                step: Mutex::new(Duration::zero()),
+            })
+        }
+
+        fn jump(&self, step: Duration) {
+            let mut total = self.step.lock().unwrap();
+            *total = *total + step;
+        }
+    }
+
+    impl TimeSource for TestClock {
+        fn now(&self) -> DateTime<Local> {
+            let elapsed = Duration::from_std(Instant::now() - self.started).unwrap();
+            self.start + elapsed + *self.step.lock().unwrap()
+        }
+    }
+
+    fn at(h: u32, m: u32, s: u32, ms: u32) -> DateTime<Local> {
+        Local.ymd(2022, 1, 15).and_hms_milli(h, m, s, ms)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ticks_on_boundaries() {
+        let clock = TestClock::new(at(12, 0, 0, 300));
+        let mut scheduler = Scheduler::new(clock.clone());
+
+        let tick = scheduler.tick().await;
+        assert_eq!(tick.time, at(12, 0, 0, 500));
+        assert!(!tick.first_half);
+        assert!(!tick.jumped);
+        assert_eq!(clock.now(), at(12, 0, 0, 500));
+
+        let tick = scheduler.tick().await;
+        assert_eq!(tick.time, at(12, 0, 1, 0));
+        assert!(tick.first_half);
+        assert_eq!(clock.now(), at(12, 0, 1, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn boundary_within_tolerance_is_current() {
+        let clock = TestClock::new(at(12, 0, 0, 980));
+        let mut scheduler = Scheduler::new(clock);
+
+        assert_eq!(scheduler.tick().await.time, at(12, 0, 1, 0));
+        // the same boundary is never returned twice
+        assert_eq!(scheduler.tick().await.time, at(12, 0, 1, 500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skips_missed_ticks() {
+        let clock = TestClock::new(at(12, 0, 0, 0));
+        let mut scheduler = Scheduler::new(clock);
+
+        assert_eq!(scheduler.tick().await.time, at(12, 0, 0, 0));
+        // long drawing
+        time::sleep(std::time::Duration::from_millis(1200)).await;
+        let tick = scheduler.tick().await;
+        assert_eq!(tick.time, at(12, 0, 1, 500));
+        assert!(!tick.jumped);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn detects_jumps() {
+        let clock = TestClock::new(at(12, 0, 0, 0));
+        let mut scheduler = Scheduler::new(clock.clone());
+        scheduler.tick().await;
+
+        clock.jump(Duration::hours(1) + Duration::milliseconds(200));
+        let tick = scheduler.tick().await;
+        assert!(tick.jumped);
+        assert_eq!(tick.time, at(13, 0, 0, 500));
+
+        let tick = scheduler.tick().await;
+        assert!(!tick.jumped);
+        assert_eq!(tick.time, at(13, 0, 1, 0));
+
+        // back in time, the boundary of the previous tick comes again
+        clock.jump(-Duration::seconds(10));
+        let tick = scheduler.tick().await;
+        assert!(tick.jumped);
+        assert_eq!(tick.time, at(12, 59, 51, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn frozen_time_alternates_halves() {
+        let clock = time_source::from_options(Some("2022-01-15T23:59:59"), Some("0"))
+            .unwrap()
+            .unwrap();
+        let mut scheduler = Scheduler::new(clock);
+
+        let halves = [scheduler.tick().await, scheduler.tick().await, scheduler.tick().await];
+        assert!(halves.iter().all(|tick| tick.time == at(23, 59, 59, 0) && !tick.jumped));
+        assert_eq!(
+            halves.iter().map(|tick| tick.first_half).collect::<Vec<_>>(),
+            [true, false, true]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn scaled_time_is_not_aligned() {
+        let start = at(6, 0, 0, 123);
+        let clock = Arc::new(SimulatedClock::new(start, 60.0));
+        let mut scheduler = Scheduler::new(clock);
+
+        let started = Instant::now();
+        let first = scheduler.tick().await;
+        let second = scheduler.tick().await;
+        // every real half-second whatever the simulated time is
+        assert_eq!(Instant::now() - started, std::time::Duration::from_millis(1000));
+        assert!(first.first_half && !second.first_half);
+        assert!(first.time >= start && second.time >= first.time);
+    }
+}
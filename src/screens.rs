@@ -0,0 +1,145 @@
+use chrono::{DateTime, Local, Utc};
+use std::time::Duration;
+use tokio::time;
+
+use crate::{
+    agenda::{self, Agenda},
+    countdown::Countdown,
+    display::LinearMatrixDisplay,
+    error::Error,
+    graph,
+    history::Quantity,
+    model::{Config, Screen},
+    moon,
+    sampler::Sampler,
+    sensor::Capabilities,
+    sun, text,
+    weather::{self, Observations, WeatherType},
+};
+
+/// Screens shown between the clock faces and the state they keep
+pub(crate) struct Screens<'a> {
+    config: &'a Config,
+    sampler: &'a Sampler,
+    countdown: Countdown<'a>,
+    agenda: Option<Agenda<'a>>,
+}
+
+impl<'a> Screens<'a> {
+    /// Fails on screens which can not be shown with the config
+    pub fn new(config: &'a Config, sampler: &'a Sampler) -> Result<Self, Error> {
+        let humidity = sampler.capabilities().contains(Capabilities::HUMIDITY);
+        for screen in &config.screens {
+            match screen {
+                Screen::Sun if config.location.is_none() => return Err(Error::LocationError {}),
+                Screen::Agenda if config.agenda.is_none() => return Err(Error::AgendaConfigError {}),
+                Screen::DewPoint | Screen::HeatIndex | Screen::Humidex | Screen::AbsoluteHumidity | Screen::Comfort
+                    if !humidity =>
+                {
+                    return Err(Error::ScreenHumidityError { screen: format!("{:?}", screen) })
+                }
+                Screen::Sun
+                | Screen::Moon
+                | Screen::Countdown
+                | Screen::Agenda
+                | Screen::TemperatureGraph
+                | Screen::HumidityGraph
+                | Screen::PressureGraph
+                | Screen::Co2Graph
+                | Screen::DewPoint
+                | Screen::HeatIndex
+                | Screen::Humidex
+                | Screen::AbsoluteHumidity
+                | Screen::Comfort
+                | Screen::MinMax => {}
+            }
+        }
+        Ok(Screens {
+            config,
+            sampler,
+            countdown: Countdown::new(&config.countdown)?,
+            agenda: config.agenda.as_ref().map(Agenda::new),
+        })
+    }
+
+    /// Shows the screen once, text ones are scrolled through the display
+    pub async fn show(
+        &mut self,
+        display: &mut LinearMatrixDisplay,
+        screen: Screen,
+        time: &DateTime<Local>,
+        observations: &Observations,
+    ) -> Result<(), Error> {
+        let config = self.config;
+        match screen {
+            Screen::Sun => {
+                let location = config.location.as_ref().ok_or(Error::LocationError {})?;
+                text::scroll(display, &sun::text(time, location), config.display.scroll_msec).await
+            }
+            Screen::Moon => {
+                let phase = moon::phase(&time.with_timezone(&Utc));
+                let days = (phase.next_full - time.with_timezone(&Utc)).num_hours() as f64 / 24.0;
+                let icon = phase.icon();
+                // the icon stays while the illumination and the days to the full moon follow each other
+                for label in [format!("{:.0}%", phase.illumination() * 100.0), format!("{:.0}d", days)] {
+                    let mut columns = icon.to_vec();
+                    columns.extend_from_slice(&[0, 0]);
+                    columns.extend(text::render(&label));
+                    text::draw_columns(display, &columns, 0)?;
+                    time::sleep(Duration::from_millis(config.display.screen_msec)).await;
+                }
+                Ok(())
+            }
+            Screen::Countdown => match self.countdown.text(time, config.language) {
+                Some(text) => text::scroll(display, &text, config.display.scroll_msec).await,
+                None => Ok(()),
+            },
+            Screen::Agenda => match self.agenda.as_mut().and_then(|a| a.text(time, config.language)) {
+                Some(text) => text::scroll(display, &text, config.display.scroll_msec).await,
+                None => Ok(()),
+            },
+            Screen::TemperatureGraph | Screen::HumidityGraph | Screen::PressureGraph | Screen::Co2Graph => {
+                let quantity = match screen {
+                    Screen::TemperatureGraph => Quantity::Temperature,
+                    Screen::HumidityGraph => Quantity::Humidity,
+                    Screen::PressureGraph => Quantity::Pressure,
+                    _ => Quantity::Co2,
+                };
+                // the sampler is not blocked while the graph is shown
+                let history = self.sampler.history().lock().unwrap().clone();
+                graph::show(display, &history, quantity, time.timestamp(), config).await
+            }
+            Screen::DewPoint | Screen::HeatIndex | Screen::Humidex | Screen::AbsoluteHumidity | Screen::Comfort => {
+                let weather_type = match screen {
+                    Screen::DewPoint => WeatherType::DewPoint,
+                    Screen::HeatIndex => WeatherType::HeatIndex,
+                    Screen::Humidex => WeatherType::Humidex,
+                    Screen::AbsoluteHumidity => WeatherType::AbsoluteHumidity,
+                    _ => WeatherType::Comfort,
+                };
+                weather::show_derived(display, self.sampler, weather_type, config).await
+            }
+            Screen::MinMax => weather::show_ranges(display, observations, config).await,
+        }
+    }
+
+    /// Celebrates the countdown event which has just come or reminds of the calendar one,
+    /// `false` when there is nothing to show
+    pub async fn notify(&mut self, display: &mut LinearMatrixDisplay, time: &DateTime<Local>) -> Result<bool, Error> {
+        if let Some(name) = self.countdown.reached(time) {
+            println!("Event `{}` has come", name);
+            self.countdown
+                .celebrate(display, name, self.config.display.scroll_msec)
+                .await?;
+            return Ok(true);
+        }
+        let language = self.config.language;
+        match self.agenda.as_mut().and_then(|agenda| agenda.reminder(time, language)) {
+            Some(text) => {
+                agenda::flash(display, &text, self.config.display.scroll_msec).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use embedded_hal::prelude::_embedded_hal_blocking_delay_DelayMs;
+use i2cdev::linux::LinuxI2CError;
+use linux_embedded_hal::{Delay, I2cdev};
+use snafu::ResultExt;
+use std::{
+    fmt,
+    fs::File,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    aht10::{self, AHT10},
+    bme280::{self, Filter, Oversampling, BME280},
+    ds18b20::Ds18b20,
+    error::{self, Error},
+    mhz19::{self, Mhz19},
+    model::{self, AhtVariant, SensorMode},
+    scd4x::{self, Scd4x},
+    sht::{self, Family, Sht},
+};
+
+/// Humidity the heater is turned on at
+const HEATER_HUMIDITY: f32 = 95.0;
+/// The heater is turned on once in this period at most, it wears the sensor and warms the room reading
+const HEATER_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Measurements are dropped while the heated sensor cools down
+const HEATER_COOL_DOWN: Duration = Duration::from_secs(30);
+
+bitflags! {
+    pub(crate) struct Capabilities: u8 {
+        const TEMPERATURE = (1 << 0);
+        const HUMIDITY = (1 << 1);
+        const PRESSURE = (1 << 2);
+        const CO2 = (1 << 3);
+    }
+}
+
+/// Values measured at once, the ones the sensor is not capable of are `None`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Measurement {
+    /// celsius
+    pub temperature: f32,
+    /// relative, 0-100%
+    pub humidity: Option<f32>,
+    /// hPa
+    pub pressure: Option<f32>,
+    /// ppm
+    pub co2: Option<f32>,
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1}°C", self.temperature)?;
+        if let Some(humidity) = self.humidity {
+            write!(f, ", {:.0}%", humidity)?;
+        }
+        if let Some(pressure) = self.pressure {
+            write!(f, ", {:.0} hPa", pressure)?;
+        }
+        if let Some(co2) = self.co2 {
+            write!(f, ", {:.0} ppm", co2)?;
+        }
+        Ok(())
+    }
+}
+
+/// Climate sensor shown on the weather screens.
+/// To add a driver implement the trait for it and register the name in `open`.
+pub(crate) trait Sensor: Send {
+    fn capabilities(&self) -> Capabilities;
+
+    /// Measures blocking the thread until the result is ready
+    fn measure(&mut self) -> Result<Measurement, Error>;
+
+    /// Triggers a measurement collected by `poll`, returns the time it takes at least.
+    /// Drivers which cannot split the measurement do it all in `poll`.
+    fn start(&mut self) -> Result<Duration, Error> {
+        Ok(Duration::ZERO)
+    }
+
+    /// Result of the started measurement, `None` while it is in progress
+    fn poll(&mut self) -> Result<Option<Measurement>, Error> {
+        self.measure().map(Some)
+    }
+}
+
+/// Opens the sensor by the driver name used in the config
+pub(crate) fn open(config: &model::WeatherSensor) -> Result<Box<dyn Sensor>, Error> {
+    let sensor: Box<dyn Sensor> = match config.driver.as_str() {
+        "aht10" => {
+            let i2c = I2cdev::new(&config.gpio_dev).context(error::I2CSnafu)?;
+            let variant = match config.variant {
+                AhtVariant::Aht10 => aht10::Variant::Aht10,
+                AhtVariant::Aht20 | AhtVariant::Aht21 => aht10::Variant::Aht2x,
+            };
+            let mut sensor = AHT10::new(i2c, variant, |ms| Delay {}.delay_ms(ms));
+            sensor.init().context(error::SensorSnafu)?;
+            Box::new(sensor)
+        }
+        "bme280" => {
+            let i2c = I2cdev::new(&config.gpio_dev).context(error::I2CSnafu)?;
+            let address = config.address.unwrap_or(bme280::I2C_ADDRESS);
+            let mut sensor = BME280::new(i2c, address, |ms| Delay {}.delay_ms(ms));
+            let oversampling = Oversampling::from_samples(config.oversampling).ok_or(Error::SensorOptionError {
+                option: "oversampling".to_string(),
+                value: config.oversampling,
+            })?;
+            let filter = Filter::from_coefficient(config.filter)
+                .ok_or(Error::SensorOptionError { option: "filter".to_string(), value: config.filter })?;
+            sensor
+                .init(bme280::Settings {
+                    temperature: oversampling,
+                    pressure: oversampling,
+                    humidity: oversampling,
+                    filter,
+                    mode: match config.mode() {
+                        SensorMode::SingleShot => bme280::Mode::Forced,
+                        SensorMode::Periodic => bme280::Mode::Normal,
+                    },
+                })
+                .context(error::Bme280Snafu)?;
+            Box::new(sensor)
+        }
+        "sht3x" | "sht4x" => {
+            let i2c = I2cdev::new(&config.gpio_dev).context(error::I2CSnafu)?;
+            let address = config.address.unwrap_or(sht::I2C_ADDRESS);
+            let family = if config.driver == "sht3x" {
+                Family::Sht3x
+            } else {
+                Family::Sht4x
+            };
+            let mut sensor = Sht::new(i2c, address, family, |ms| Delay {}.delay_ms(ms));
+            sensor
+                .init(config.mode() == SensorMode::Periodic)
+                .context(error::ShtSnafu)?;
+            Box::new(ShtSensor {
+                sensor,
+                heater: config.heater.then(Heater::default),
+            })
+        }
+        "scd4x" => {
+            let i2c = I2cdev::new(&config.gpio_dev).context(error::I2CSnafu)?;
+            let mut sensor = Scd4x::new(i2c, |ms| Delay {}.delay_ms(ms));
+            sensor
+                .init(config.mode() == SensorMode::Periodic)
+                .map_err(scd4x_error)?;
+            Box::new(sensor)
+        }
+        "mhz19" => Box::new(Mhz19::open(&config.gpio_dev).context(error::Mhz19Snafu)?),
+        _ => return Err(Error::SensorDriverError { name: config.driver.clone() }),
+    };
+    Ok(sensor)
+}
+
+/// Main sensor measured along with a CO2 one, the CO2 of the latter replaces the main one
+pub(crate) struct WithCo2 {
+    sensor: Box<dyn Sensor>,
+    co2_sensor: Box<dyn Sensor>,
+    /// results of the started measurements
+    pending: (Option<Measurement>, Option<Measurement>),
+}
+
+impl WithCo2 {
+    pub fn new(sensor: Box<dyn Sensor>, co2_sensor: Box<dyn Sensor>) -> Self {
+        WithCo2 { sensor, co2_sensor, pending: (None, None) }
+    }
+
+    fn merge(measurement: Measurement, co2: &Measurement) -> Measurement {
+        Measurement { co2: co2.co2, ..measurement }
+    }
+}
+
+impl Sensor for WithCo2 {
+    fn capabilities(&self) -> Capabilities {
+        self.sensor.capabilities() | Capabilities::CO2
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error> {
+        let measurement = self.sensor.measure()?;
+        Ok(Self::merge(measurement, &self.co2_sensor.measure()?))
+    }
+
+    fn start(&mut self) -> Result<Duration, Error> {
+        self.pending = (None, None);
+        Ok(self.sensor.start()?.max(self.co2_sensor.start()?))
+    }
+
+    fn poll(&mut self) -> Result<Option<Measurement>, Error> {
+        if self.pending.0.is_none() {
+            self.pending.0 = self.sensor.poll()?;
+        }
+        if self.pending.1.is_none() {
+            self.pending.1 = self.co2_sensor.poll()?;
+        }
+        Ok(match self.pending {
+            (Some(measurement), Some(co2)) => Some(Self::merge(measurement, &co2)),
+            _ => None,
+        })
+    }
+}
+
+impl Sensor for AHT10<I2cdev> {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::TEMPERATURE | Capabilities::HUMIDITY
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error> {
+        let (temperature, humidity) = AHT10::measure(self).map_err(aht_error)?;
+        Ok(aht_measurement(temperature, humidity))
+    }
+
+    fn start(&mut self) -> Result<Duration, Error> {
+        self.start_measurement().map_err(aht_error)?;
+        Ok(Duration::from_millis(aht10::MEASURE_MS as u64))
+    }
+
+    fn poll(&mut self) -> Result<Option<Measurement>, Error> {
+        let result = self.poll_result().map_err(aht_error)?;
+        Ok(result.map(|(temperature, humidity)| aht_measurement(temperature, humidity)))
+    }
+}
+
+fn aht_measurement(temperature: f32, humidity: f32) -> Measurement {
+    Measurement {
+        temperature,
+        humidity: Some(humidity),
+        pressure: None,
+        co2: None,
+    }
+}
+
+fn aht_error(e: aht10::error::Error<LinuxI2CError>) -> Error {
+    match e {
+        aht10::error::Error::CrcError {} => Error::SensorCrcError {},
+        e => Error::SensorError { source: e },
+    }
+}
+
+impl Sensor for BME280<I2cdev> {
+    fn capabilities(&self) -> Capabilities {
+        let mut capabilities = Capabilities::TEMPERATURE | Capabilities::PRESSURE;
+        capabilities.set(Capabilities::HUMIDITY, self.has_humidity());
+        capabilities
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error> {
+        let (temperature, pressure, humidity) = BME280::measure(self).context(error::Bme280Snafu)?;
+        Ok(Measurement {
+            temperature,
+            humidity,
+            pressure: Some(pressure / 100.0),
+            co2: None,
+        })
+    }
+}
+
+impl Sensor for Ds18b20 {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::TEMPERATURE
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error> {
+        Ok(Measurement {
+            temperature: self.read()?,
+            humidity: None,
+            pressure: None,
+            co2: None,
+        })
+    }
+}
+
+impl Sensor for Scd4x<I2cdev> {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::CO2 | Capabilities::TEMPERATURE | Capabilities::HUMIDITY
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error> {
+        let (co2, temperature, humidity) = Scd4x::measure(self).map_err(scd4x_error)?;
+        Ok(Measurement {
+            temperature,
+            humidity: Some(humidity),
+            pressure: None,
+            co2: Some(co2 as f32),
+        })
+    }
+}
+
+/// The temperature is the one of the sensor internals, a few degrees above the room one
+impl Sensor for Mhz19<File> {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::CO2 | Capabilities::TEMPERATURE
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error> {
+        let (co2, temperature) = Mhz19::measure(self).map_err(|e| match e {
+            mhz19::error::Error::ChecksumError {} => Error::SensorCrcError {},
+            e => Error::Mhz19Error { source: e },
+        })?;
+        Ok(Measurement {
+            temperature,
+            humidity: None,
+            pressure: None,
+            co2: Some(co2 as f32),
+        })
+    }
+}
+
+/// When the heater of the sensor is turned on
+#[derive(Debug, Default)]
+struct Heater {
+    heated: Option<Instant>,
+}
+
+impl Heater {
+    /// `true` while the sensor is still warm after heating
+    fn cooling(&self, now: Instant) -> bool {
+        self.heated
+            .is_some_and(|heated| now.saturating_duration_since(heated) < HEATER_COOL_DOWN)
+    }
+
+    /// `true` when the sensor is to be heated after the measurement, the time is taken as the heating one
+    fn due(&mut self, humidity: f32, now: Instant) -> bool {
+        let due = humidity >= HEATER_HUMIDITY
+            && self
+                .heated
+                .is_none_or(|heated| now.saturating_duration_since(heated) >= HEATER_INTERVAL);
+        if due {
+            self.heated = Some(now);
+        }
+        due
+    }
+}
+
+/// SHT sensor with the heater policy
+struct ShtSensor {
+    sensor: Sht<I2cdev>,
+    /// `None` when the heater is not used
+    heater: Option<Heater>,
+}
+
+impl Sensor for ShtSensor {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::TEMPERATURE | Capabilities::HUMIDITY
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error> {
+        if self
+            .heater
+            .as_ref()
+            .is_some_and(|heater| heater.cooling(Instant::now()))
+        {
+            return Err(Error::SensorCoolingError {});
+        }
+        let (temperature, humidity) = self.sensor.measure().map_err(sht_error)?;
+        if self
+            .heater
+            .as_mut()
+            .is_some_and(|heater| heater.due(humidity, Instant::now()))
+        {
+            // the measurement is taken before the heating, the ones during the cool-down are dropped
+            self.sensor.heat().map_err(sht_error)?;
+        }
+        Ok(Measurement {
+            temperature,
+            humidity: Some(humidity),
+            pressure: None,
+            co2: None,
+        })
+    }
+}
+
+/// CRC errors are told apart to be retried
+fn sht_error(e: sht::error::Error<LinuxI2CError>) -> Error {
+    match e {
+        sht::error::Error::CrcError {} => Error::SensorCrcError {},
+        e => Error::ShtError { source: e },
+    }
+}
+
+fn scd4x_error(e: scd4x::error::Error<LinuxI2CError>) -> Error {
+    match e {
+        scd4x::error::Error::CrcError {} => Error::SensorCrcError {},
+        e => Error::Scd4xError { source: e },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heats_once_per_interval() {
+        let mut heater = Heater::default();
+        let start = Instant::now();
+        assert!(!heater.due(HEATER_HUMIDITY - 1.0, start));
+        assert!(!heater.cooling(start));
+
+        assert!(heater.due(HEATER_HUMIDITY, start));
+        assert!(heater.cooling(start + HEATER_COOL_DOWN / 2));
+        assert!(!heater.cooling(start + HEATER_COOL_DOWN));
+
+        // condensation is not heated away again till the interval passes
+        assert!(!heater.due(100.0, start + HEATER_COOL_DOWN));
+        assert!(!heater.due(100.0, start + HEATER_INTERVAL / 2));
+        assert!(heater.due(100.0, start + HEATER_INTERVAL));
+        assert!(heater.cooling(start + HEATER_INTERVAL));
+    }
+
+    /// Sensor with the measurement ready after the given number of polls
+    struct Fake {
+        measurement: Measurement,
+        polls: usize,
+        pending: usize,
+    }
+
+    impl Sensor for Fake {
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::TEMPERATURE
+        }
+
+        fn measure(&mut self) -> Result<Measurement, Error> {
+            Ok(self.measurement)
+        }
+
+        fn start(&mut self) -> Result<Duration, Error> {
+            self.pending = self.polls;
+            Ok(Duration::from_millis(self.polls as u64))
+        }
+
+        fn poll(&mut self) -> Result<Option<Measurement>, Error> {
+            // a measurement is taken once
+            assert!(self.pending > 0, "polled after the result");
+            self.pending -= 1;
+            Ok((self.pending == 0).then_some(self.measurement))
+        }
+    }
+
+    fn fake(temperature: f32, humidity: Option<f32>, co2: Option<f32>, polls: usize) -> Box<dyn Sensor> {
+        let measurement = Measurement { temperature, humidity, pressure: None, co2 };
+        Box::new(Fake { measurement, polls, pending: 0 })
+    }
+
+    #[test]
+    fn takes_co2_from_co2_sensor() {
+        let mut sensor = WithCo2::new(fake(21.5, Some(40.0), None, 1), fake(30.0, None, Some(650.0), 3));
+        assert!(sensor.capabilities().contains(Capabilities::CO2));
+        assert_eq!(sensor.start().unwrap(), Duration::from_millis(3));
+        assert!(sensor.poll().unwrap().is_none());
+        assert!(sensor.poll().unwrap().is_none());
+        let measurement = sensor.poll().unwrap().unwrap();
+        assert_eq!(
+            (measurement.temperature, measurement.humidity, measurement.co2),
+            (21.5, Some(40.0), Some(650.0))
+        );
+    }
+
+    #[test]
+    fn scd4x_measures_periodically_by_default() {
+        let mut config = model::Config::new().weather.sensor;
+        assert_eq!(config.mode(), SensorMode::SingleShot);
+        config.driver = "scd4x".to_string();
+        assert_eq!(config.mode(), SensorMode::Periodic);
+        config.mode = Some(SensorMode::SingleShot);
+        assert_eq!(config.mode(), SensorMode::SingleShot);
+    }
+}
@@ -0,0 +1,20 @@
+use snafu::Snafu;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum Error<I2CError>
+where
+    I2CError: 'static + std::error::Error,
+{
+    #[snafu(display("SHT read error"))]
+    ReadError { source: I2CError },
+
+    #[snafu(display("SHT write error"))]
+    WriteError { source: I2CError },
+
+    #[snafu(display("SHT data CRC mismatch"))]
+    CrcError {},
+
+    #[snafu(display("SHT4x has no periodic mode"))]
+    PeriodicModeError {},
+}
@@ -0,0 +1,158 @@
+pub mod error;
+
+use embedded_hal::blocking::i2c::{Read, Write};
+use error::Error;
+use snafu::ResultExt;
+
+use crate::crc::crc8;
+
+pub const I2C_ADDRESS: u8 = 0x44; // 0x45 for SHT3x with ADDR pulled up
+
+// SHT3x commands
+const SHT3X_SINGLE_SHOT: [u8; 2] = [0x24, 0x00]; // high repeatability, no clock stretching
+const SHT3X_PERIODIC: [u8; 2] = [0x21, 0x30]; // high repeatability, 1 measurement per second
+const SHT3X_FETCH: [u8; 2] = [0xE0, 0x00];
+const SHT3X_BREAK: [u8; 2] = [0x30, 0x93]; // stops periodic measurements
+const SHT3X_HEATER_ON: [u8; 2] = [0x30, 0x6D];
+const SHT3X_HEATER_OFF: [u8; 2] = [0x30, 0x66];
+const SHT3X_SOFT_RESET: [u8; 2] = [0x30, 0xA2];
+
+// SHT4x commands
+const SHT4X_MEASURE: u8 = 0xFD; // high precision
+const SHT4X_HEAT: u8 = 0x39; // 200 mW for 1 second, then high precision measurement
+const SHT4X_SOFT_RESET: u8 = 0x94;
+
+const SHT3X_MEASURE_MS: u32 = 16;
+const SHT4X_MEASURE_MS: u32 = 9;
+const HEAT_MS: u32 = 1100;
+
+/// Sensirion humidity sensor family, they differ in commands and humidity conversion
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    Sht3x,
+    Sht4x,
+}
+
+/// Sensirion SHT3x or SHT4x temperature and humidity sensor
+pub struct Sht<I2C> {
+    i2c: I2C,
+    delay_ms: fn(u32) -> (),
+    address: u8,
+    family: Family,
+    periodic: bool,
+}
+
+impl<I2C, I2CError> Sht<I2C>
+where
+    I2CError: std::error::Error,
+    I2C: Read<Error = I2CError> + Write<Error = I2CError>,
+{
+    pub fn new(i2c: I2C, address: u8, family: Family, delay_ms: fn(u32) -> ()) -> Self {
+        Sht { i2c, delay_ms, address, family, periodic: false }
+    }
+
+    /// Resets the sensor and starts periodic measurements when they are requested (SHT3x only)
+    pub fn init(&mut self, periodic: bool) -> Result<(), Error<I2CError>> {
+        if periodic && self.family == Family::Sht4x {
+            return Err(Error::PeriodicModeError {});
+        }
+        match self.family {
+            Family::Sht3x => {
+                // a reset is not accepted while measuring periodically
+                self.write(&SHT3X_BREAK)?;
+                (self.delay_ms)(1);
+                self.write(&SHT3X_SOFT_RESET)?;
+            }
+            Family::Sht4x => self.write(&[SHT4X_SOFT_RESET])?,
+        }
+        (self.delay_ms)(2);
+
+        if periodic {
+            self.write(&SHT3X_PERIODIC)?;
+            (self.delay_ms)(SHT3X_MEASURE_MS);
+        }
+        self.periodic = periodic;
+        Ok(())
+    }
+
+    /// Measures Temperature and Relative Humidity
+    pub fn measure(&mut self) -> Result<(f32, f32), Error<I2CError>> {
+        match (self.family, self.periodic) {
+            (Family::Sht3x, true) => self.write(&SHT3X_FETCH)?,
+            (Family::Sht3x, false) => {
+                self.write(&SHT3X_SINGLE_SHOT)?;
+                (self.delay_ms)(SHT3X_MEASURE_MS);
+            }
+            (Family::Sht4x, _) => {
+                self.write(&[SHT4X_MEASURE])?;
+                (self.delay_ms)(SHT4X_MEASURE_MS);
+            }
+        }
+        self.read_measurement()
+    }
+
+    /// Heats the sensor for about a second to evaporate condensed water, measurements are off for a while after it
+    pub fn heat(&mut self) -> Result<(), Error<I2CError>> {
+        match self.family {
+            Family::Sht3x => {
+                self.write(&SHT3X_HEATER_ON)?;
+                (self.delay_ms)(HEAT_MS);
+                self.write(&SHT3X_HEATER_OFF)
+            }
+            Family::Sht4x => {
+                // the heater turns off by itself, the measurement made at the end is not needed
+                self.write(&[SHT4X_HEAT])?;
+                (self.delay_ms)(HEAT_MS);
+                self.read_measurement().map(|_| ())
+            }
+        }
+    }
+
+    fn read_measurement(&mut self) -> Result<(f32, f32), Error<I2CError>> {
+        let mut buf = [0; 6];
+        self.i2c.read(self.address, &mut buf).context(error::ReadSnafu)?;
+        let temperature = word(&buf[0..3])?;
+        let humidity = word(&buf[3..6])?;
+
+        let temperature = -45.0 + 175.0 * temperature as f32 / 65535.0;
+        let humidity = match self.family {
+            Family::Sht3x => 100.0 * humidity as f32 / 65535.0,
+            Family::Sht4x => -6.0 + 125.0 * humidity as f32 / 65535.0,
+        };
+        Ok((temperature, humidity.clamp(0.0, 100.0)))
+    }
+
+    fn write(&mut self, command: &[u8]) -> Result<(), Error<I2CError>> {
+        self.i2c.write(self.address, command).context(error::WriteSnafu)
+    }
+}
+
+/// 16-bit word followed by its CRC
+fn word<E>(data: &[u8]) -> Result<u16, Error<E>>
+where
+    E: 'static + std::error::Error,
+{
+    if crc8(&data[0..2]) != data[2] {
+        return Err(Error::CrcError {});
+    }
+    Ok(u16::from_be_bytes([data[0], data[1]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    #[test]
+    fn checks_word_crc() {
+        assert!(matches!(word::<Infallible>(&[0xBE, 0xEF, 0x92]), Ok(0xBEEF)));
+        assert!(matches!(
+            word::<Infallible>(&[0xBE, 0xEF, 0x93]),
+            Err(Error::CrcError {})
+        ));
+        assert!(matches!(
+            word::<Infallible>(&[0xBE, 0xEE, 0x92]),
+            Err(Error::CrcError {})
+        ));
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::model::Location;
+
+/// Julian day of 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+/// Julian day of the unix epoch
+const UNIX_EPOCH_JD: f64 = 2440587.5;
+/// Earth axial tilt, degrees
+const OBLIQUITY: f64 = 23.4397;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SunEvent {
+    /// civil dawn, the sun is 6° below the horizon
+    Dawn,
+    Sunrise,
+    Sunset,
+    /// civil dusk, the sun is 6° below the horizon
+    Dusk,
+}
+
+impl SunEvent {
+    /// Altitude of the sun center at the event: refraction and the disk radius are counted for sunrise/sunset
+    fn altitude(self) -> f64 {
+        match self {
+            SunEvent::Sunrise | SunEvent::Sunset => -0.833,
+            SunEvent::Dawn | SunEvent::Dusk => -6.0,
+        }
+    }
+
+    fn morning(self) -> bool {
+        matches!(self, SunEvent::Dawn | SunEvent::Sunrise)
+    }
+}
+
+/// Time of the event on the given local date, `None` when it does not happen (polar day or night).
+/// NOAA sunrise equation, accurate to a minute or so between the polar circles.
+pub(crate) fn event(date: NaiveDate, location: &Location, event: SunEvent) -> Option<DateTime<Utc>> {
+    let (latitude, longitude) = (location.latitude.to_radians(), location.longitude);
+
+    // days since J2000 at the local noon
+    let days = (date - NaiveDate::from_ymd(2000, 1, 1)).num_days() as f64;
+    let noon = days - longitude / 360.0;
+
+    let anomaly = (357.5291 + 0.98560028 * noon).rem_euclid(360.0).to_radians();
+    let center = 1.9148 * anomaly.sin() + 0.0200 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let ecliptic = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit = J2000 + noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic).sin();
+
+    let declination = (ecliptic.sin() * OBLIQUITY.to_radians().sin()).asin();
+    let cos_hour_angle = (event.altitude().to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+
+    let julian = if event.morning() {
+        transit - hour_angle
+    } else {
+        transit + hour_angle
+    };
+    let seconds = ((julian - UNIX_EPOCH_JD) * 86400.0).round() as i64;
+    Some(Utc.timestamp(seconds, 0))
+}
+
+/// Text for the sun screen: "☀ 07:42 ☾ 18:31"
+pub(crate) fn text(time: &DateTime<Local>, location: &Location) -> String {
+    let date = time.date().naive_local();
+    let format = |e| match event(date, location, e) {
+        Some(t) => t.with_timezone(&Local).format("%H:%M").to_string(),
+        None => "--:--".to_string(),
+    };
+    format!("☀ {} ☾ {}", format(SunEvent::Sunrise), format(SunEvent::Sunset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minutes the events may differ from the NOAA solar calculator
+    const TOLERANCE: i64 = 2;
+
+    fn at(latitude: f64, longitude: f64) -> Location {
+        Location { latitude, longitude }
+    }
+
+    fn assert_near(actual: Option<DateTime<Utc>>, expected: &str) {
+        let expected = DateTime::parse_from_rfc3339(expected).unwrap().with_timezone(&Utc);
+        let actual = actual.expect("the event happens");
+        let minutes = (actual - expected).num_minutes().abs();
+        assert!(
+            minutes <= TOLERANCE,
+            "{} is {} minutes off {}",
+            actual,
+            minutes,
+            expected
+        );
+    }
+
+    #[test]
+    fn london_solstices() {
+        let london = at(51.5074, -0.1278);
+        let summer = NaiveDate::from_ymd(2021, 6, 21);
+        assert_near(event(summer, &london, SunEvent::Sunrise), "2021-06-21T03:43:00Z");
+        assert_near(event(summer, &london, SunEvent::Sunset), "2021-06-21T20:21:00Z");
+        let winter = NaiveDate::from_ymd(2021, 12, 21);
+        assert_near(event(winter, &london, SunEvent::Sunrise), "2021-12-21T08:04:00Z");
+        assert_near(event(winter, &london, SunEvent::Sunset), "2021-12-21T15:54:00Z");
+    }
+
+    #[test]
+    fn equator_equinox() {
+        let equator = at(0.0, 0.0);
+        let date = NaiveDate::from_ymd(2021, 3, 20);
+        assert_near(event(date, &equator, SunEvent::Sunrise), "2021-03-20T06:04:00Z");
+        assert_near(event(date, &equator, SunEvent::Sunset), "2021-03-20T18:11:00Z");
+    }
+
+    #[test]
+    fn southern_hemisphere() {
+        // Sydney rises on the previous UTC day
+        let sydney = at(-33.8688, 151.2093);
+        let date = NaiveDate::from_ymd(2021, 12, 21);
+        assert_near(event(date, &sydney, SunEvent::Sunrise), "2021-12-20T18:41:00Z");
+        assert_near(event(date, &sydney, SunEvent::Sunset), "2021-12-21T09:05:00Z");
+    }
+
+    #[test]
+    fn polar_day_and_night() {
+        let tromso = at(69.6496, 18.956);
+        let summer = NaiveDate::from_ymd(2021, 6, 21);
+        assert_eq!(event(summer, &tromso, SunEvent::Sunrise), None);
+        assert_eq!(event(summer, &tromso, SunEvent::Sunset), None);
+        assert_eq!(event(summer, &tromso, SunEvent::Dusk), None);
+
+        let winter = NaiveDate::from_ymd(2021, 12, 21);
+        assert_eq!(event(winter, &tromso, SunEvent::Sunrise), None);
+        assert_eq!(event(winter, &tromso, SunEvent::Sunset), None);
+        // the sun stays a few degrees below the horizon, civil twilight still comes at noon
+        let dawn = event(winter, &tromso, SunEvent::Dawn).unwrap();
+        let dusk = event(winter, &tromso, SunEvent::Dusk).unwrap();
+        assert!(dawn < dusk);
+        assert_near(Some(dawn + (dusk - dawn) / 2), "2021-12-21T10:42:00Z");
+    }
+}
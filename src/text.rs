@@ -0,0 +1,234 @@
+use std::time::Duration;
+use tokio::time;
+
+use crate::{display::LinearMatrixDisplay, error::Error};
+
+/// Classic 5x7 font for ASCII 0x20..=0x7E, glyphs are columns where bit 0 is the top row
+const ASCII: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x00, 0x08, 0x14, 0x22, 0x41], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x41, 0x22, 0x14, 0x08, 0x00], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x01, 0x01], // F
+    [0x3E, 0x41, 0x41, 0x51, 0x32], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x04, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x7F, 0x20, 0x18, 0x20, 0x7F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x03, 0x04, 0x78, 0x04, 0x03], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x00, 0x7F, 0x41, 0x41], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x41, 0x41, 0x7F, 0x00, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x08, 0x14, 0x54, 0x54, 0x3C], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x00, 0x7F, 0x10, 0x28, 0x44], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+/// Cyrillic letters А..=я (U+0410..=U+044F), same layout as `ASCII`
+const CYRILLIC: [[u8; 5]; 64] = [
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // А
+    [0x7F, 0x49, 0x49, 0x49, 0x31], // Б
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // В
+    [0x7F, 0x01, 0x01, 0x01, 0x01], // Г
+    [0x60, 0x3E, 0x21, 0x3F, 0x60], // Д
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // Е
+    [0x77, 0x08, 0x7F, 0x08, 0x77], // Ж
+    [0x22, 0x41, 0x49, 0x49, 0x36], // З
+    [0x7F, 0x20, 0x10, 0x08, 0x7F], // И
+    [0x7E, 0x21, 0x11, 0x09, 0x7E], // Й
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // К
+    [0x40, 0x3E, 0x01, 0x01, 0x7F], // Л
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // М
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // Н
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // О
+    [0x7F, 0x01, 0x01, 0x01, 0x7F], // П
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // Р
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // С
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // Т
+    [0x27, 0x48, 0x48, 0x48, 0x3F], // У
+    [0x0E, 0x11, 0x7F, 0x11, 0x0E], // Ф
+    [0x63, 0x14, 0x08, 0x14, 0x63], // Х
+    [0x3F, 0x20, 0x20, 0x3F, 0x60], // Ц
+    [0x07, 0x08, 0x08, 0x08, 0x7F], // Ч
+    [0x7F, 0x40, 0x7F, 0x40, 0x7F], // Ш
+    [0x3F, 0x20, 0x3F, 0x20, 0x7F], // Щ
+    [0x01, 0x7F, 0x48, 0x48, 0x30], // Ъ
+    [0x7F, 0x48, 0x30, 0x00, 0x7F], // Ы
+    [0x7F, 0x48, 0x48, 0x48, 0x30], // Ь
+    [0x22, 0x41, 0x49, 0x49, 0x3E], // Э
+    [0x7F, 0x08, 0x3E, 0x41, 0x3E], // Ю
+    [0x46, 0x29, 0x19, 0x09, 0x7F], // Я
+    [0x20, 0x54, 0x54, 0x54, 0x78], // а
+    [0x3C, 0x4A, 0x4A, 0x4A, 0x31], // б
+    [0x7C, 0x54, 0x54, 0x54, 0x28], // в
+    [0x7C, 0x04, 0x04, 0x04, 0x04], // г
+    [0x60, 0x38, 0x24, 0x3C, 0x60], // д
+    [0x38, 0x54, 0x54, 0x54, 0x18], // е
+    [0x6C, 0x10, 0x7C, 0x10, 0x6C], // ж
+    [0x28, 0x44, 0x54, 0x54, 0x28], // з
+    [0x7C, 0x20, 0x10, 0x08, 0x7C], // и
+    [0x7C, 0x21, 0x12, 0x09, 0x7C], // й
+    [0x7C, 0x10, 0x28, 0x44, 0x00], // к
+    [0x40, 0x38, 0x04, 0x04, 0x7C], // л
+    [0x7C, 0x08, 0x10, 0x08, 0x7C], // м
+    [0x7C, 0x10, 0x10, 0x10, 0x7C], // н
+    [0x38, 0x44, 0x44, 0x44, 0x38], // о
+    [0x7C, 0x04, 0x04, 0x04, 0x7C], // п
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // р
+    [0x38, 0x44, 0x44, 0x44, 0x20], // с
+    [0x04, 0x04, 0x7C, 0x04, 0x04], // т
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // у
+    [0x18, 0x24, 0x7E, 0x24, 0x18], // ф
+    [0x44, 0x28, 0x10, 0x28, 0x44], // х
+    [0x3C, 0x20, 0x20, 0x3C, 0x60], // ц
+    [0x0C, 0x10, 0x10, 0x10, 0x7C], // ч
+    [0x7C, 0x40, 0x7C, 0x40, 0x7C], // ш
+    [0x3C, 0x20, 0x3C, 0x20, 0x7C], // щ
+    [0x04, 0x7C, 0x50, 0x50, 0x20], // ъ
+    [0x7C, 0x50, 0x20, 0x00, 0x7C], // ы
+    [0x7C, 0x50, 0x50, 0x50, 0x20], // ь
+    [0x28, 0x44, 0x54, 0x54, 0x38], // э
+    [0x7C, 0x10, 0x38, 0x44, 0x38], // ю
+    [0x48, 0x34, 0x14, 0x14, 0x7C], // я
+];
+const CYRILLIC_YO: [u8; 5] = [0x7C, 0x55, 0x54, 0x55, 0x44]; // Ё
+const CYRILLIC_YO_SMALL: [u8; 5] = [0x38, 0x55, 0x54, 0x55, 0x18]; // ё
+
+const SUN: [u8; 7] = [0x08, 0x22, 0x1C, 0x5D, 0x1C, 0x22, 0x08]; // ☀
+const MOON: [u8; 4] = [0x3E, 0x63, 0x41, 0x41]; // ☾
+const DEGREE: [u8; 4] = [0x06, 0x09, 0x09, 0x06]; // °
+
+/// Shown for characters missing in the font
+const UNKNOWN: [u8; 5] = [0x7F, 0x41, 0x41, 0x41, 0x7F];
+
+/// Columns of the character glyph with the empty ones around it trimmed
+fn glyph(c: char) -> Vec<u8> {
+    let columns: &[u8] = match c {
+        ' ' => return vec![0, 0],
+        ' '..='~' => &ASCII[c as usize - ' ' as usize],
+        'А'..='я' => &CYRILLIC[c as usize - 'А' as usize],
+        'Ё' => &CYRILLIC_YO,
+        'ё' => &CYRILLIC_YO_SMALL,
+        '☀' => &SUN,
+        '☾' => &MOON,
+        '°' => &DEGREE,
+        _ => &UNKNOWN,
+    };
+    let first = columns.iter().position(|c| *c != 0).unwrap_or(0);
+    let last = columns.iter().rposition(|c| *c != 0).unwrap_or(0);
+    columns[first..=last].to_vec()
+}
+
+/// Renders the text as display columns (bit 0 is the top row) with one empty column between characters.
+/// Glyphs take rows 1..=7 to share the baseline with the digits.
+pub(crate) fn render(text: &str) -> Vec<u8> {
+    let mut columns = vec![];
+    for (i, c) in text.chars().enumerate() {
+        if i > 0 {
+            columns.push(0);
+        }
+        columns.extend(glyph(c).into_iter().map(|column| column << 1));
+    }
+    columns
+}
+
+/// Draws the columns with the first one at `x`, it might be outside the display
+pub(crate) fn draw_columns(display: &mut LinearMatrixDisplay, columns: &[u8], x: isize) -> Result<(), Error> {
+    display.draw(|px, py| {
+        let column = px as isize - x;
+        if column >= 0 && (column as usize) < columns.len() {
+            (columns[column as usize] >> py) & 1
+        } else {
+            0
+        }
+    })
+}
+
+/// Moves the text from the right edge of the display to the left one until it disappears
+pub(crate) async fn scroll(display: &mut LinearMatrixDisplay, text: &str, step_msec: u64) -> Result<(), Error> {
+    let columns = render(text);
+    let width = display.width() as isize;
+    for x in (-(columns.len() as isize)..=width).rev() {
+        draw_columns(display, &columns, x)?;
+        time::sleep(Duration::from_millis(step_msec)).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::{sync::Arc, time::Instant};
+
+use crate::error::{self, Error};
+
+/// The fastest `--time-scale`, a day passes in under a tenth of a second
+const MAX_SCALE: f64 = 1_000_000.0;
+
+/// Source of the wall-clock time. Everything that shows or schedules by the time of day asks it
+/// instead of `chrono::Local::now()`, so the time can be frozen or fast-forwarded.
+pub(crate) trait TimeSource: Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+
+    /// How many seconds of this clock pass during one real second
+    fn scale(&self) -> f64 {
+        1.0
+    }
+
+    /// Whether the time can be trusted, e.g. it is synchronised by NTP
+    fn synchronized(&self) -> bool {
+        system_synchronized()
+    }
+}
+
+/// The system clock
+pub(crate) struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// Clock frozen at the given time
+pub(crate) struct FixedClock(DateTime<Local>);
+
+impl TimeSource for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.0
+    }
+
+    fn scale(&self) -> f64 {
+        0.0
+    }
+
+    fn synchronized(&self) -> bool {
+        true
+    }
+}
+
+/// Clock started at the given time and running `scale` times faster than the real one
+pub(crate) struct SimulatedClock {
+    start: DateTime<Local>,
+    started: Instant,
+    scale: f64,
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<Local>, scale: f64) -> Self {
+        SimulatedClock { start, started: Instant::now(), scale }
+    }
+
+    /// The time after `elapsed` real time
+    fn at(&self, elapsed: std::time::Duration) -> DateTime<Local> {
+        let elapsed = elapsed.mul_f64(self.scale);
+        self.start + Duration::from_std(elapsed).unwrap_or_else(|_| Duration::zero())
+    }
+}
+
+impl TimeSource for SimulatedClock {
+    fn now(&self) -> DateTime<Local> {
+        self.at(self.started.elapsed())
+    }
+
+    fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    fn synchronized(&self) -> bool {
+        true
+    }
+}
+
+/// System clock which falls back to the RTC time while the system time is not synchronised (e.g. by NTP)
+pub(crate) struct RtcFallbackClock {
+    rtc: DateTime<Utc>,
+    read_at: Instant,
+}
+
+impl RtcFallbackClock {
+    /// `rtc` is the time read from the RTC just now
+    pub fn new(rtc: DateTime<Utc>) -> Self {
+        RtcFallbackClock { rtc, read_at: Instant::now() }
+    }
+}
+
+impl TimeSource for RtcFallbackClock {
+    fn now(&self) -> DateTime<Local> {
+        if system_synchronized() {
+            return Local::now();
+        }
+        let elapsed = Duration::from_std(self.read_at.elapsed()).unwrap_or_else(|_| Duration::zero());
+        (self.rtc + elapsed).with_timezone(&Local)
+    }
+}
+
+/// Asks the kernel whether the system time is synchronised
+pub(crate) fn system_synchronized() -> bool {
+    // SAFETY: `timex` is a plain C struct, zero `modes` makes `adjtimex` read-only
+    let state = unsafe {
+        let mut timex: libc::timex = std::mem::zeroed();
+        libc::adjtimex(&mut timex)
+    };
+    state >= 0 && state != libc::TIME_ERROR
+}
+
+/// Steps the system time, requires `CAP_SYS_TIME`
+pub(crate) fn set_system_time(time: DateTime<Utc>) -> std::io::Result<()> {
+    let spec = libc::timespec {
+        tv_sec: time.timestamp() as libc::time_t,
+        tv_nsec: time.timestamp_subsec_nanos() as _,
+    };
+    // SAFETY: `spec` is a valid timespec living for the whole call
+    if unsafe { libc::clock_settime(libc::CLOCK_REALTIME, &spec) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Builds the time source from `--fake-time` and `--time-scale` options, `None` when both are absent.
+/// Time scale `0` freezes the clock.
+pub(crate) fn from_options(
+    fake_time: Option<&str>,
+    time_scale: Option<&str>,
+) -> Result<Option<Arc<dyn TimeSource>>, Error> {
+    let scale = match time_scale {
+        Some(value) => match value.parse::<f64>() {
+            Ok(scale) if (0.0..=MAX_SCALE).contains(&scale) => scale,
+            _ => return error::TimeOptionSnafu { value }.fail(),
+        },
+        None => 1.0,
+    };
+    let start = match fake_time {
+        Some(value) => parse_local(value).ok_or_else(|| error::TimeOptionSnafu { value }.build())?,
+        None if time_scale.is_none() => return Ok(None),
+        None => Local::now(),
+    };
+
+    if scale == 0.0 {
+        Ok(Some(Arc::new(FixedClock(start))))
+    } else {
+        Ok(Some(Arc::new(SimulatedClock::new(start, scale))))
+    }
+}
+
+/// Parses local time as `YYYY-MM-DD[THH:MM[:SS]]`, the earlier one is taken for ambiguous DST times
+pub(crate) fn parse_local(value: &str) -> Option<DateTime<Local>> {
+    parse_in(value, &Local)
+}
+
+fn parse_in<Tz: TimeZone>(value: &str, zone: &Tz) -> Option<DateTime<Tz>> {
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M"))
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|date| date.and_hms(0, 0, 0)))
+        .ok()?;
+    zone.from_local_datetime(&naive).earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Timelike};
+    use chrono_tz::Europe::Berlin;
+
+    #[test]
+    fn rejects_bad_scales() {
+        for scale in ["-1", "inf", "NaN", "1e300", "fast"] {
+            assert!(from_options(None, Some(scale)).is_err(), "{}", scale);
+        }
+        assert!(from_options(None, Some("0")).unwrap().is_some());
+        assert!(from_options(None, Some("1000000")).unwrap().is_some());
+        assert!(from_options(None, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn simulated_clock_crosses_midnight() {
+        let clock = SimulatedClock::new(Local.ymd(2022, 1, 15).and_hms(23, 59, 30), 60.0);
+        let time = clock.at(std::time::Duration::from_secs(1));
+        assert_eq!((time.day(), time.hour(), time.minute(), time.second()), (16, 0, 0, 30));
+    }
+
+    #[test]
+    fn simulated_clock_crosses_dst() {
+        // 2022-03-27 02:00 CET jumps to 03:00 CEST
+        let start = Berlin.ymd(2022, 3, 27).and_hms(1, 59, 0).with_timezone(&Local);
+        let clock = SimulatedClock::new(start, 60.0);
+        let time = clock.at(std::time::Duration::from_secs(2)).with_timezone(&Berlin);
+        assert_eq!((time.hour(), time.minute()), (3, 1));
+        assert_eq!(time - start.with_timezone(&Berlin), Duration::minutes(2));
+    }
+
+    #[test]
+    fn parses_local_times() {
+        let time = parse_in("2022-01-15T23:59", &Berlin).unwrap();
+        assert_eq!(time.naive_local(), NaiveDate::from_ymd(2022, 1, 15).and_hms(23, 59, 0));
+        let time = parse_in("2022-01-15", &Berlin).unwrap();
+        assert_eq!(time.naive_local(), NaiveDate::from_ymd(2022, 1, 15).and_hms(0, 0, 0));
+        assert!(parse_in("2022-01-15T25:00", &Berlin).is_none());
+    }
+
+    #[test]
+    fn skips_nonexistent_times() {
+        // 02:30 does not exist when the clocks go forward
+        assert!(parse_in("2022-03-27T02:30", &Berlin).is_none());
+    }
+
+    #[test]
+    fn takes_earlier_ambiguous_time() {
+        // 02:30 happens twice when the clocks go back, first in CEST (UTC+2)
+        let time = parse_in("2022-10-30T02:30", &Berlin).unwrap();
+        assert_eq!(time.with_timezone(&Utc), Utc.ymd(2022, 10, 30).and_hms(0, 30, 0));
+    }
+}
@@ -0,0 +1,363 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    clock,
+    control::{Action, Command},
+    display::{self, LinearMatrixDisplay},
+    error::{self, Error},
+    model,
+};
+
+/// How long a lap split stays on the display
+const LAP_ON_DISPLAY: Duration = Duration::from_secs(3);
+/// Two digits of hours fit the display, the stopwatch stays at 99:59:59 after that
+const MAX_SECONDS: u64 = 100 * 3600 - 1;
+
+/// Countdown timer or stopwatch, takes over the display from the clock face while it exists.
+/// Time is measured with the monotonic clock, so it is not affected by system time changes.
+pub(crate) struct Timer {
+    /// `None` for the stopwatch
+    countdown: Option<Duration>,
+    /// time accumulated before the last start
+    elapsed: Duration,
+    started: Option<Instant>,
+    laps: Vec<Duration>,
+    lap_shown_until: Option<Instant>,
+}
+
+impl Timer {
+    pub fn countdown(duration: Duration) -> Self {
+        Timer {
+            countdown: Some(duration),
+            elapsed: Duration::ZERO,
+            started: None,
+            laps: vec![],
+            lap_shown_until: None,
+        }
+    }
+
+    pub fn stopwatch() -> Self {
+        Timer {
+            countdown: None,
+            ..Timer::countdown(Duration::ZERO)
+        }
+    }
+
+    pub fn is_countdown(&self) -> bool {
+        self.countdown.is_some()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        self.elapsed
+            + self
+                .started
+                .map_or(Duration::ZERO, |started| now.saturating_duration_since(started))
+    }
+
+    pub fn start(&mut self, now: Instant) {
+        if self.started.is_none() && self.overrun(now).is_none() {
+            self.started = Some(now);
+        }
+    }
+
+    pub fn pause(&mut self, now: Instant) {
+        self.elapsed = self.elapsed(now);
+        self.started = None;
+    }
+
+    pub fn toggle(&mut self, now: Instant) {
+        if self.is_running() {
+            self.pause(now);
+        } else {
+            self.start(now);
+        }
+    }
+
+    /// Stops the timer and brings it to the initial state
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.started = None;
+        self.laps.clear();
+        self.lap_shown_until = None;
+    }
+
+    /// Records a lap split of the running stopwatch and returns it
+    pub fn lap(&mut self, now: Instant) -> Option<Duration> {
+        if self.is_countdown() || !self.is_running() {
+            return None;
+        }
+        let split = self.elapsed(now) - self.laps.iter().sum::<Duration>();
+        self.laps.push(split);
+        self.lap_shown_until = Some(now + LAP_ON_DISPLAY);
+        Some(split)
+    }
+
+    pub fn laps(&self) -> &[Duration] {
+        &self.laps
+    }
+
+    /// Time passed since the countdown reached zero, `None` while it is still counting
+    pub fn overrun(&self, now: Instant) -> Option<Duration> {
+        let duration = self.countdown?;
+        self.elapsed(now).checked_sub(duration)
+    }
+
+    /// Whole seconds to show: remaining time (rounded up) for the countdown,
+    /// elapsed time or the last lap split for the stopwatch
+    fn shown_seconds(&self, now: Instant) -> u64 {
+        match self.countdown {
+            Some(duration) => {
+                let remaining = duration.saturating_sub(self.elapsed(now));
+                remaining.as_millis().div_ceil(1000) as u64
+            }
+            None => match (self.laps.last(), self.lap_shown_until) {
+                (Some(lap), Some(until)) if now < until => lap.as_secs(),
+                _ => self.elapsed(now).as_secs(),
+            },
+        }
+    }
+
+    /// Draws `MM:SS` with big digits or `HH:MM:SS` with tiny ones when it is an hour or longer.
+    /// Dots blink while running and stay lit while paused, finished countdown blinks entirely.
+    pub fn draw(
+        &self,
+        display: &mut LinearMatrixDisplay,
+        now: Instant,
+        draw_dots: bool,
+        slim: bool,
+    ) -> Result<(), Error> {
+        if self.overrun(now).is_some() && !draw_dots {
+            return display.clear();
+        }
+        let draw_dots = draw_dots || !self.is_running();
+
+        let seconds = self.shown_seconds(now).min(MAX_SECONDS);
+        let (hours, minutes, seconds) = (seconds / 3600, (seconds / 60 % 60), (seconds % 60));
+        if hours == 0 {
+            return clock::draw_pair(display, minutes as u32, seconds as u32, draw_dots, slim);
+        }
+
+        let nums = display::TINY_NUMS;
+        let semicolon = display::TINY_SEMICOLON;
+        let h1 = nums[(hours / 10) as usize];
+        let h2 = nums[(hours % 10) as usize];
+        let m1 = nums[(minutes / 10) as usize];
+        let m2 = nums[(minutes % 10) as usize];
+        let s1 = nums[(seconds / 10) as usize];
+        let s2 = nums[(seconds % 10) as usize];
+
+        display.draw(|x, y| {
+            if (1..=3).contains(&x) && hours >= 10 {
+                h1[y] & (1 << (3 - x))
+            } else if (5..=7).contains(&x) {
+                h2[y] & (1 << (7 - x))
+            } else if (x == 9 || x == 19) && draw_dots {
+                semicolon[y]
+            } else if (11..=13).contains(&x) {
+                m1[y] & (1 << (13 - x))
+            } else if (15..=17).contains(&x) {
+                m2[y] & (1 << (17 - x))
+            } else if (21..=23).contains(&x) {
+                s1[y] & (1 << (23 - x))
+            } else if (25..=27).contains(&x) {
+                s2[y] & (1 << (27 - x))
+            } else {
+                0
+            }
+        })
+    }
+}
+
+/// Applies the command to the current timer, creating or dropping it when needed
+pub(crate) fn apply(
+    timer: &mut Option<Timer>,
+    command: &Command,
+    config: &model::Timer,
+    now: Instant,
+) -> Result<(), Error> {
+    match command {
+        Command::Timer { action, duration } => {
+            let current = timer.as_mut().filter(|t| t.is_countdown());
+            match (action, current) {
+                (Action::Start, Some(t)) if duration.is_none() => t.start(now),
+                (Action::Toggle, Some(t)) => t.toggle(now),
+                (Action::Start | Action::Toggle, _) => {
+                    let mut countdown = Timer::countdown(preset_duration(config, duration.as_deref())?);
+                    countdown.start(now);
+                    *timer = Some(countdown);
+                }
+                (Action::Pause, Some(t)) => t.pause(now),
+                (Action::Reset, Some(t)) => t.reset(),
+                (Action::Stop, Some(_)) => *timer = None,
+                _ => {}
+            }
+        }
+        Command::Stopwatch { action } => {
+            let current = timer.as_mut().filter(|t| !t.is_countdown());
+            match (action, current) {
+                (Action::Start, Some(t)) => t.start(now),
+                (Action::Toggle, Some(t)) => t.toggle(now),
+                (Action::Start | Action::Toggle, None) => {
+                    let mut stopwatch = Timer::stopwatch();
+                    stopwatch.start(now);
+                    *timer = Some(stopwatch);
+                }
+                (Action::Pause, Some(t)) => t.pause(now),
+                (Action::Reset, Some(t)) => t.reset(),
+                (Action::Lap, Some(t)) => {
+                    if let Some(split) = t.lap(now) {
+                        println!("Lap {}: {}", t.laps().len(), format_duration(split));
+                    }
+                }
+                (Action::Stop, Some(_)) => *timer = None,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolves a preset name or a duration (`90`, `3:00`, `1:30:00`), the first preset is used when nothing passed.
+/// Durations which do not fit the display are rejected.
+fn preset_duration(config: &model::Timer, duration: Option<&str>) -> Result<Duration, Error> {
+    let duration = match duration {
+        Some(duration) => duration,
+        None => config.presets.first().map(|p| p.name.as_str()).unwrap_or_default(),
+    };
+    let resolved = match config.presets.iter().find(|p| p.name == duration) {
+        Some(preset) => Some(Duration::from_secs(preset.duration_sec)),
+        None => parse_duration(duration),
+    };
+    resolved
+        .filter(|resolved| resolved.as_secs() <= MAX_SECONDS)
+        .ok_or_else(|| error::TimerDurationSnafu { duration }.build())
+}
+
+/// Parses `SS`, `MM:SS` or `H:MM:SS`, `None` for zero and the durations which do not fit the display
+pub(crate) fn parse_duration(text: &str) -> Option<Duration> {
+    let parts = text
+        .split(':')
+        .map(|p| p.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let seconds = parts
+        .iter()
+        .try_fold(0u64, |total, part| total.checked_mul(60)?.checked_add(*part))?;
+    (1..=MAX_SECONDS)
+        .contains(&seconds)
+        .then(|| Duration::from_secs(seconds))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}.{}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        duration.subsec_millis() / 100
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Some(secs(90)));
+        assert_eq!(parse_duration("3:00"), Some(secs(180)));
+        assert_eq!(parse_duration("1:30:00"), Some(secs(5400)));
+        assert_eq!(parse_duration("99:59:59"), Some(secs(MAX_SECONDS)));
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("0:00"), None);
+        assert_eq!(parse_duration("100:00:00"), None);
+        assert_eq!(parse_duration("1:0:0:0"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("1:-5"), None);
+        assert_eq!(parse_duration("abc"), None);
+        // too big to be multiplied, not a panic
+        assert_eq!(parse_duration("18446744073709551615:0"), None);
+        assert_eq!(parse_duration("307445734561825860:0:0"), None);
+        assert_eq!(parse_duration("18446744073709551615"), None);
+    }
+
+    #[test]
+    fn countdown_expires() {
+        let start = Instant::now();
+        let mut timer = Timer::countdown(secs(60));
+        timer.start(start);
+        assert!(timer.is_running());
+        assert_eq!(timer.shown_seconds(start), 60);
+        // the remaining time is rounded up
+        assert_eq!(timer.shown_seconds(start + Duration::from_millis(59_001)), 1);
+        assert_eq!(timer.overrun(start + Duration::from_millis(59_999)), None);
+        assert_eq!(timer.overrun(start + secs(60)), Some(Duration::ZERO));
+        assert_eq!(timer.overrun(start + secs(65)), Some(secs(5)));
+        assert_eq!(timer.shown_seconds(start + secs(65)), 0);
+
+        // a finished countdown is not started again
+        timer.pause(start + secs(65));
+        timer.start(start + secs(70));
+        assert!(!timer.is_running());
+        timer.reset();
+        timer.start(start + secs(70));
+        assert_eq!(timer.shown_seconds(start + secs(80)), 50);
+    }
+
+    #[test]
+    fn pauses_and_resumes() {
+        let start = Instant::now();
+        let mut timer = Timer::countdown(secs(60));
+        timer.start(start);
+        timer.toggle(start + secs(10));
+        assert!(!timer.is_running());
+        // the paused time does not count
+        assert_eq!(timer.elapsed(start + secs(100)), secs(10));
+        timer.toggle(start + secs(100));
+        assert_eq!(timer.elapsed(start + secs(120)), secs(30));
+        assert_eq!(timer.shown_seconds(start + secs(120)), 30);
+        assert_eq!(timer.overrun(start + secs(150)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn stopwatch_laps() {
+        let start = Instant::now();
+        let mut stopwatch = Timer::stopwatch();
+        assert_eq!(stopwatch.lap(start), None);
+        stopwatch.start(start);
+        assert_eq!(stopwatch.lap(start + secs(30)), Some(secs(30)));
+        assert_eq!(stopwatch.lap(start + secs(75)), Some(secs(45)));
+        // the split is shown for a while, then the elapsed time again
+        assert_eq!(stopwatch.shown_seconds(start + secs(76)), 45);
+        assert_eq!(stopwatch.shown_seconds(start + secs(75) + LAP_ON_DISPLAY), 78);
+        assert_eq!(stopwatch.overrun(start + secs(1000)), None);
+    }
+
+    #[test]
+    fn applies_commands() {
+        let config = model::Timer::default();
+        let start = Instant::now();
+        let mut timer = None;
+        let command =
+            |action, duration: Option<&str>| Command::Timer { action, duration: duration.map(str::to_string) };
+        apply(&mut timer, &command(Action::Start, Some("1:30")), &config, start).unwrap();
+        assert_eq!(timer.as_ref().unwrap().shown_seconds(start), 90);
+        apply(&mut timer, &command(Action::Pause, None), &config, start + secs(10)).unwrap();
+        apply(&mut timer, &command(Action::Start, None), &config, start + secs(20)).unwrap();
+        assert_eq!(timer.as_ref().unwrap().shown_seconds(start + secs(30)), 70);
+        assert!(apply(&mut timer, &command(Action::Start, Some("100:00:00")), &config, start).is_err());
+        apply(&mut timer, &command(Action::Stop, None), &config, start).unwrap();
+        assert!(timer.is_none());
+    }
+}
@@ -1,92 +1,902 @@
-use crate::aht10::AHT10;
-use linux_embedded_hal::I2cdev;
-use snafu::ResultExt;
+use chrono::{DateTime, Local, NaiveDate};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use tokio::time;
 
 use crate::{
+    buzzer::Buzzer,
+    comfort::{self, Comfort},
     display,
-    error::{self, Error},
+    error::Error,
+    model::{self, Co2Alert, Language, PressureUnit, TemperatureUnit},
+    sampler::{Readings, Sampler},
+    sensor::{Capabilities, Measurement},
+    text,
 };
 
 pub(crate) enum WeatherType {
-    Humidity,
-    Temperature,
+    Humidity(Option<Trend>),
+    Temperature(Option<Trend>),
+    Pressure(PressureUnit),
+    Co2(Co2Level),
+    DewPoint,
+    HeatIndex,
+    Humidex,
+    AbsoluteHumidity,
+    /// the comfort icon next to the humidity
+    Comfort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub(crate) enum Co2Level {
+    Good,
+    Fair,
+    Poor,
+}
+
+/// The level goes down only when CO2 is that much below its threshold, no alerts on each wobble around it
+const CO2_HYSTERESIS_PPM: f32 = 50.0;
+const ALERT_BLINKS: usize = 5;
+const ALERT_BLINK_MSEC: u64 = 200;
+
+/// Keeps the CO2 level between measurements to raise an alert once it goes up
+struct Co2Watch {
+    level: Co2Level,
 }
 
-pub(crate) struct Weather {
-    humidity: u8,
-    temperature: f32,
+impl Co2Watch {
+    fn new() -> Self {
+        Co2Watch { level: Co2Level::Good }
+    }
+
+    /// Updates the level, returns the alert of the threshold if it has just been reached
+    fn update(&mut self, co2: f32, config: &model::Co2) -> Option<Co2Alert> {
+        let reached = |threshold: &model::Co2Threshold, level| {
+            let hysteresis = if self.level >= level { CO2_HYSTERESIS_PPM } else { 0.0 };
+            co2 >= threshold.ppm as f32 - hysteresis
+        };
+        let level = if reached(&config.danger, Co2Level::Poor) {
+            Co2Level::Poor
+        } else if reached(&config.warning, Co2Level::Fair) {
+            Co2Level::Fair
+        } else {
+            Co2Level::Good
+        };
+
+        let raised = level > self.level;
+        self.level = level;
+        let alert = match level {
+            Co2Level::Good => Co2Alert::None,
+            Co2Level::Fair => config.warning.alert,
+            Co2Level::Poor => config.danger.alert,
+        };
+        (raised && alert != Co2Alert::None).then_some(alert)
+    }
 }
 
-pub(crate) fn draw(
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+/// Values of the latest minutes with the time they were measured at
+struct Series {
+    values: VecDeque<(Instant, f32)>,
+}
+
+impl Series {
+    fn new() -> Self {
+        Series { values: VecDeque::new() }
+    }
+
+    /// The same reading seen again is skipped, the ones older than the window are dropped
+    fn push(&mut self, time: Instant, value: f32, window: Duration) {
+        if self.values.back().is_some_and(|(last, _)| *last >= time) {
+            return;
+        }
+        self.values.push_back((time, value));
+        while self
+            .values
+            .front()
+            .is_some_and(|(first, _)| time.duration_since(*first) > window)
+        {
+            self.values.pop_front();
+        }
+    }
+
+    /// Change of the latest value from the oldest one, `None` until the values span the half of the window
+    fn trend(&self, window: Duration, threshold: f32) -> Option<Trend> {
+        let ((first_time, first), (last_time, last)) = (self.values.front()?, self.values.back()?);
+        if last_time.duration_since(*first_time) < window / 2 {
+            return None;
+        }
+        let change = last - first;
+        Some(if change >= threshold {
+            Trend::Rising
+        } else if change <= -threshold {
+            Trend::Falling
+        } else {
+            Trend::Steady
+        })
+    }
+}
+
+/// Lowest and highest values
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    min: f32,
+    max: f32,
+}
+
+fn widen(range: &mut Option<Range>, value: f32) {
+    *range = Some(match *range {
+        Some(Range { min, max }) => Range { min: min.min(value), max: max.max(value) },
+        None => Range { min: value, max: value },
+    });
+}
+
+/// What the weather screens have seen, kept between draws
+pub(crate) struct Observations {
+    config: model::Trend,
+    co2: Co2Watch,
+    temperature: Series,
+    humidity: Series,
+    /// day of the ranges, they start over at the local midnight
+    date: Option<NaiveDate>,
+    temperature_range: Option<Range>,
+    humidity_range: Option<Range>,
+    probe_ranges: Vec<Option<Range>>,
+}
+
+impl Observations {
+    pub fn new(config: &model::Weather) -> Self {
+        Observations {
+            config: config.trend,
+            co2: Co2Watch::new(),
+            temperature: Series::new(),
+            humidity: Series::new(),
+            date: None,
+            temperature_range: None,
+            humidity_range: None,
+            probe_ranges: Vec::new(),
+        }
+    }
+
+    /// Takes the fresh readings into account, called on every tick not to miss a measurement
+    pub fn observe(&mut self, sampler: &Sampler, time: &DateTime<Local>) {
+        self.record(&sampler.latest(), sampler.max_age(), time);
+    }
+
+    fn record(&mut self, readings: &Readings, max_age: Duration, time: &DateTime<Local>) {
+        let date = time.date().naive_local();
+        if self.date != Some(date) {
+            self.date = Some(date);
+            self.temperature_range = None;
+            self.humidity_range = None;
+            self.probe_ranges.clear();
+        }
+
+        let window = self.window();
+        if let (Some(measurement), Some(taken)) = (readings.sensor.fresh(max_age), readings.sensor.time) {
+            self.temperature.push(taken, measurement.temperature, window);
+            widen(&mut self.temperature_range, measurement.temperature);
+            if let Some(humidity) = measurement.humidity {
+                self.humidity.push(taken, humidity, window);
+                widen(&mut self.humidity_range, humidity);
+            }
+        }
+        self.probe_ranges.resize(readings.probes.len(), None);
+        for (range, probe) in self.probe_ranges.iter_mut().zip(&readings.probes) {
+            if let Some(measurement) = probe.fresh(max_age) {
+                widen(range, measurement.temperature);
+            }
+        }
+    }
+
+    fn window(&self) -> Duration {
+        Duration::from_secs(self.config.window_min.max(1) * 60)
+    }
+}
+
+/// Glyphs of the weather screens in one of the display styles
+struct Font {
+    nums: [[u8; 8]; 10],
+    digit_width: usize,
+    minus_width: usize,
+    dot_width: usize,
+    dot: [u8; 8],
+    minus: [u8; 8],
+    percent: [u8; 8],
+    humidity: [u8; 8],
+    celsius: [u8; 8],
+    fahrenheit: [u8; 8],
+    kelvin: [u8; 8],
+    hectopascal: [u8; 8],
+    millimetre: [u8; 8],
+}
+
+const FONT: Font = Font {
+    nums: display::NUMS,
+    digit_width: 6,
+    minus_width: 4,
+    dot_width: 2,
+    dot: display::DOT,
+    minus: display::MINUS,
+    percent: display::PERCENT,
+    humidity: display::HUMIDITY,
+    celsius: display::CELSIUS,
+    fahrenheit: display::FAHRENHEIT,
+    kelvin: display::KELVIN,
+    hectopascal: display::HECTOPASCAL,
+    millimetre: display::MILLIMETRE,
+};
+
+const SLIM_FONT: Font = Font {
+    nums: display::SLIM_NUMS,
+    digit_width: 6,
+    minus_width: 4,
+    dot_width: 2,
+    dot: display::SLIM_DOT,
+    minus: display::SLIM_MINUS,
+    percent: display::SLIM_PERCENT,
+    humidity: display::SLIM_HUMIDITY,
+    celsius: display::SLIM_CELSIUS,
+    fahrenheit: display::SLIM_FAHRENHEIT,
+    kelvin: display::SLIM_KELVIN,
+    hectopascal: display::SLIM_HECTOPASCAL,
+    millimetre: display::SLIM_MILLIMETRE,
+};
+
+/// Numbers only, the slim units are never used with it
+const TINY_FONT: Font = Font {
+    nums: display::TINY_NUMS,
+    digit_width: 3,
+    minus_width: 2,
+    dot_width: 1,
+    dot: display::TINY_DOT,
+    minus: display::TINY_MINUS,
+    ..SLIM_FONT
+};
+
+/// Glyph rows (the lowest `width` bits, the highest one is on the left) and the gap after it
+type Glyph<'a> = (&'a [u8; 8], usize, usize);
+
+/// Shows the temperature and the rest the sensor is capable of, one screen after another,
+/// then the temperature of each probe next to the sensor one.
+/// Stale or failed readings are shown as dashes, the temperature and the humidity have their trend arrows.
+/// Returns the CO2 alert to raise.
+pub(crate) async fn show(
     display: &mut display::LinearMatrixDisplay,
-    sensor: &mut AHT10<I2cdev>,
-    weather_type: WeatherType,
+    sampler: &Sampler,
+    observations: &mut Observations,
+    config: &model::Weather,
     slim: bool,
-) -> Result<(), Error> {
-    let weather = measure(sensor)?;
+) -> Result<Option<Co2Alert>, Error> {
+    let readings = sampler.latest();
+    let measurement = readings.sensor.fresh(sampler.max_age());
+    let capabilities = sampler.capabilities();
+    let co2 = &mut observations.co2;
+    let alert = measurement
+        .and_then(|measurement| measurement.co2)
+        .and_then(|value| co2.update(value, &config.co2));
+    let window = observations.window();
+    let trend = |series: &Series, threshold| measurement.and(series.trend(window, threshold));
 
-    let (humidity, percent, dot, celsius, nums) = if slim {
+    let screens = [
         (
-            display::SLIM_HUMIDITY,
-            display::SLIM_PERCENT,
-            display::SLIM_DOT,
-            display::SLIM_CELSIUS,
-            display::SLIM_NUMS,
-        )
-    } else {
+            WeatherType::Temperature(trend(&observations.temperature, config.trend.temperature)),
+            Capabilities::TEMPERATURE,
+            config.temperature_on_display_msec,
+        ),
         (
-            display::HUMIDITY,
-            display::PERCENT,
-            display::DOT,
-            display::CELSIUS,
-            display::NUMS,
-        )
+            WeatherType::Humidity(trend(&observations.humidity, config.trend.humidity)),
+            Capabilities::HUMIDITY,
+            config.humidity_on_display_msec,
+        ),
+        (
+            WeatherType::Pressure(config.pressure_unit),
+            Capabilities::PRESSURE,
+            config.pressure_on_display_msec,
+        ),
+        (
+            WeatherType::Co2(observations.co2.level),
+            Capabilities::CO2,
+            config.co2_on_display_msec,
+        ),
+    ];
+    for (weather_type, capability, msec) in screens {
+        if capabilities.contains(capability) {
+            draw(display, measurement, weather_type, slim, config.unit)?;
+            time::sleep(Duration::from_millis(msec)).await;
+        }
+    }
+
+    let indoor = measurement.map(|measurement| measurement.temperature);
+    for probe in &readings.probes {
+        let outdoor = probe
+            .fresh(sampler.max_age())
+            .map(|measurement| measurement.temperature);
+        draw_pair(display, indoor, outdoor, slim, config.unit)?;
+        time::sleep(Duration::from_millis(config.temperature_on_display_msec)).await;
+    }
+    Ok(alert)
+}
+
+/// Shows one of the values derived from the temperature and the humidity of the sensor
+pub(crate) async fn show_derived(
+    display: &mut display::LinearMatrixDisplay,
+    sampler: &Sampler,
+    weather_type: WeatherType,
+    config: &model::Config,
+) -> Result<(), Error> {
+    let readings = sampler.latest();
+    let measurement = readings.sensor.fresh(sampler.max_age());
+    draw(
+        display,
+        measurement,
+        weather_type,
+        config.display.slim,
+        config.weather.unit,
+    )?;
+    time::sleep(Duration::from_millis(config.display.screen_msec)).await;
+    Ok(())
+}
+
+/// Lowest and highest temperatures and humidity of the sensor since the midnight, then the temperatures of each probe.
+/// A `min/max` label scrolls before the sensor and each probe, the pairs look like the probe screen otherwise.
+pub(crate) async fn show_ranges(
+    display: &mut display::LinearMatrixDisplay,
+    observations: &Observations,
+    config: &model::Config,
+) -> Result<(), Error> {
+    let (slim, unit) = (config.display.slim, config.weather.unit);
+    let pause = Duration::from_millis(config.display.screen_msec);
+    let bounds = |range: &Option<Range>| (range.map(|range| range.min), range.map(|range| range.max));
+    let label = match config.language {
+        Language::En => "min/max",
+        Language::Ru => "мин/макс",
     };
 
-    let h1 = nums[(weather.humidity / 10) as usize];
-    let h2 = nums[(weather.humidity % 10) as usize];
+    text::scroll(display, label, config.display.scroll_msec).await?;
+    let (min, max) = bounds(&observations.temperature_range);
+    draw_pair(display, min, max, slim, unit)?;
+    time::sleep(pause).await;
+    if observations.humidity_range.is_some() {
+        let (min, max) = bounds(&observations.humidity_range);
+        let font = if slim { &SLIM_FONT } else { &FONT };
+        let variants = vec![[
+            number(&TINY_FONT, min, 0),
+            vec![(&display::BAR, 3, 0)],
+            number(&TINY_FONT, max, 0),
+            vec![(&font.percent, 6, 1)],
+        ]
+        .concat()];
+        render(display, &variants)?;
+        time::sleep(pause).await;
+    }
+    for (range, probe) in observations.probe_ranges.iter().zip(&config.weather.probes) {
+        let label = format!("{} {}", label, probe.name);
+        text::scroll(display, &label, config.display.scroll_msec).await?;
+        let (min, max) = bounds(range);
+        draw_pair(display, min, max, slim, unit)?;
+        time::sleep(pause).await;
+    }
+    Ok(())
+}
 
-    let temperature = (weather.temperature * 10.0) as u32;
-    let t1 = nums[(temperature / 100) as usize];
-    let t2 = nums[(temperature % 100 / 10) as usize];
-    let t3 = nums[(temperature % 10) as usize];
+/// Flashes the display and rings the buzzer as the alert requires
+pub(crate) async fn alert(
+    display: &mut display::LinearMatrixDisplay,
+    buzzer: Option<&mut Buzzer>,
+    alert: Co2Alert,
+) -> Result<(), Error> {
+    let flash = matches!(alert, Co2Alert::Flash | Co2Alert::FlashAndBuzzer);
+    let mut buzzer = buzzer.filter(|_| matches!(alert, Co2Alert::Buzzer | Co2Alert::FlashAndBuzzer));
+    for _ in 0..ALERT_BLINKS {
+        for on in [true, false] {
+            if flash {
+                display.draw(|_, _| on as u8)?;
+            }
+            if let Some(buzzer) = &mut buzzer {
+                buzzer.set(on)?;
+            }
+            time::sleep(Duration::from_millis(ALERT_BLINK_MSEC)).await;
+        }
+    }
+    Ok(())
+}
+
+fn draw(
+    display: &mut display::LinearMatrixDisplay,
+    measurement: Option<&Measurement>,
+    weather_type: WeatherType,
+    slim: bool,
+    unit: TemperatureUnit,
+) -> Result<(), Error> {
+    render(display, &variants(measurement, weather_type, slim, unit))
+}
+
+/// Variants from the most detailed one, the first fitting the display is drawn
+fn variants(
+    measurement: Option<&Measurement>,
+    weather_type: WeatherType,
+    slim: bool,
+    unit: TemperatureUnit,
+) -> Vec<Vec<Glyph<'static>>> {
+    let font = if slim { &SLIM_FONT } else { &FONT };
+    let derive = |formula: fn(f32, f32) -> f32| {
+        measurement.and_then(|measurement| Some(formula(measurement.temperature, measurement.humidity?)))
+    };
 
     match weather_type {
-        WeatherType::Humidity => display.draw(|x, y| {
-            if x >= 1 && x <= 7 {
-                humidity[y] & (1 << (7 - x))
-            } else if x >= 10 && x <= 15 {
-                h1[y] & (1 << (15 - x))
-            } else if x >= 17 && x <= 22 {
-                h2[y] & (1 << (22 - x))
-            } else if x >= 24 && x <= 29 {
-                percent[y] & (1 << (29 - x))
-            } else {
-                0
-            }
-        })?,
-        WeatherType::Temperature => display.draw(|x, y| {
-            if x >= 1 && x <= 6 {
-                t1[y] & (1 << (6 - x))
-            } else if x >= 8 && x <= 13 {
-                t2[y] & (1 << (13 - x))
-            } else if x >= 15 && x <= 16 {
-                dot[y] & (1 << (16 - x))
-            } else if x >= 18 && x <= 23 {
-                t3[y] & (1 << (23 - x))
-            } else if x >= 25 && x <= 30 {
-                celsius[y] & (1 << (30 - x))
-            } else {
-                0
+        WeatherType::Humidity(trend) => {
+            let humidity = measurement
+                .and_then(|measurement| measurement.humidity)
+                .map(|humidity| humidity.round().clamp(0.0, 100.0));
+            let value = number(font, humidity, 0);
+            let percent = vec![(&font.percent, 6, 1)];
+            let arrow = arrow(trend);
+            vec![
+                [
+                    vec![(&font.humidity, 7, 2)],
+                    value.clone(),
+                    percent.clone(),
+                    arrow.clone(),
+                ]
+                .concat(),
+                [value.clone(), percent.clone(), arrow].concat(),
+                [value, percent].concat(),
+            ]
+        }
+        WeatherType::Temperature(trend) => {
+            let (value, unit) = temperature(font, measurement.map(|measurement| measurement.temperature), unit);
+            let unit = vec![(unit, 6, 1)];
+            let arrow = arrow(trend);
+            let mut variants = vec![[number(font, value, 1), unit.clone(), arrow.clone()].concat()];
+            // the decimals are worth more than the unit
+            if !arrow.is_empty() {
+                variants.push([number(font, value, 1), arrow.clone()].concat());
             }
-        })?,
+            variants.extend([
+                [number(font, value, 0), unit.clone(), arrow].concat(),
+                [number(font, value, 0), unit].concat(),
+                number(font, value, 0),
+            ]);
+            variants
+        }
+        WeatherType::Pressure(unit) => {
+            let pressure = measurement.and_then(|measurement| measurement.pressure);
+            let glyph = match unit {
+                PressureUnit::Hpa => &font.hectopascal,
+                PressureUnit::Mmhg => &font.millimetre,
+            };
+            let value = pressure.map(|pressure| unit.convert(pressure));
+            let unit = vec![(glyph, 6, 1)];
+            // four digits of hPa fit only with the tiny ones
+            vec![
+                [number(font, value, 0), unit.clone()].concat(),
+                [number(&TINY_FONT, value, 0), unit].concat(),
+                number(font, value, 0),
+            ]
+        }
+        WeatherType::Co2(level) => {
+            let co2 = measurement.and_then(|measurement| measurement.co2);
+            let icon = vec![(
+                match level {
+                    Co2Level::Good => &display::CO2_GOOD,
+                    Co2Level::Fair => &display::CO2_FAIR,
+                    Co2Level::Poor => &display::CO2_POOR,
+                },
+                3,
+                2,
+            )];
+            vec![
+                [icon.clone(), number(font, co2, 0)].concat(),
+                [icon, number(&TINY_FONT, co2, 0)].concat(),
+                number(font, co2, 0),
+            ]
+        }
+        WeatherType::DewPoint | WeatherType::HeatIndex => {
+            let (formula, icon): (fn(f32, f32) -> f32, _) = match weather_type {
+                WeatherType::DewPoint => (comfort::dew_point, &display::DEW_POINT),
+                _ => (comfort::heat_index, &display::HEAT_INDEX),
+            };
+            let (value, unit) = temperature(font, derive(formula), unit);
+            let icon = vec![(icon, 3, 2)];
+            vec![
+                [icon.clone(), number(font, value, 1), vec![(unit, 6, 1)]].concat(),
+                [icon.clone(), number(font, value, 0), vec![(unit, 6, 1)]].concat(),
+                [icon, number(font, value, 0)].concat(),
+            ]
+        }
+        WeatherType::Humidex => {
+            let value = number(font, derive(comfort::humidex), 0);
+            vec![[vec![(&display::HUMIDEX, 3, 2)], value.clone()].concat(), value]
+        }
+        WeatherType::AbsoluteHumidity => {
+            let value = derive(comfort::absolute_humidity);
+            let (icon, gram) = (vec![(&font.humidity, 7, 2)], vec![(&display::GRAM, 6, 1)]);
+            vec![
+                [icon.clone(), number(font, value, 1), gram.clone()].concat(),
+                [number(font, value, 1), gram.clone()].concat(),
+                [icon, number(font, value, 0), gram].concat(),
+            ]
+        }
+        WeatherType::Comfort => {
+            let humidity = measurement.and_then(|measurement| measurement.humidity);
+            let value = number(font, humidity.map(|humidity| humidity.round().clamp(0.0, 100.0)), 0);
+            let percent = vec![(&font.percent, 6, 1)];
+            let comfort = measurement
+                .and_then(|measurement| Some(comfort::comfort(measurement.temperature, measurement.humidity?)));
+            let icon = match comfort {
+                Some(Comfort::Dry) => vec![(&display::COMFORT_DRY, 7, 2)],
+                Some(Comfort::Comfortable) => vec![(&display::COMFORT_GOOD, 7, 2)],
+                Some(Comfort::Humid) => vec![(&display::COMFORT_HUMID, 7, 2)],
+                Some(Comfort::MouldRisk) => vec![(&display::COMFORT_MOULD, 7, 2)],
+                None => vec![],
+            };
+            vec![[icon.clone(), value.clone(), percent].concat(), [icon, value].concat()]
+        }
     }
+}
 
-    Ok(())
+/// Indoor (sensor) and outdoor (probe) temperatures side by side
+fn draw_pair(
+    display: &mut display::LinearMatrixDisplay,
+    indoor: Option<f32>,
+    outdoor: Option<f32>,
+    slim: bool,
+    unit: TemperatureUnit,
+) -> Result<(), Error> {
+    let font = if slim { &SLIM_FONT } else { &FONT };
+    let (indoor, unit_glyph) = temperature(font, indoor, unit);
+    let (outdoor, _) = temperature(font, outdoor, unit);
+    let unit = vec![(unit_glyph, 6, 1)];
+    let bar = vec![(&display::BAR, 3, 0)];
+
+    let variants = vec![
+        [
+            number(font, indoor, 1),
+            bar.clone(),
+            number(font, outdoor, 1),
+            unit.clone(),
+        ]
+        .concat(),
+        [
+            number(font, indoor, 0),
+            unit.clone(),
+            bar.clone(),
+            number(font, outdoor, 0),
+            unit,
+        ]
+        .concat(),
+        [number(font, indoor, 0), bar.clone(), number(font, outdoor, 0)].concat(),
+        [
+            number(&TINY_FONT, indoor, 1),
+            bar.clone(),
+            number(&TINY_FONT, outdoor, 1),
+        ]
+        .concat(),
+        [number(&TINY_FONT, indoor, 0), bar, number(&TINY_FONT, outdoor, 0)].concat(),
+    ];
+    render(display, &variants)
+}
+
+/// The arrow glyph, nothing while the trend is unknown
+fn arrow(trend: Option<Trend>) -> Vec<Glyph<'static>> {
+    let glyph = match trend {
+        Some(Trend::Rising) => &display::TREND_RISING,
+        Some(Trend::Falling) => &display::TREND_FALLING,
+        Some(Trend::Steady) => &display::TREND_STEADY,
+        None => return vec![],
+    };
+    vec![(glyph, 3, 1)]
+}
+
+/// Temperature in the unit and the unit glyph
+fn temperature(font: &Font, celsius: Option<f32>, unit: TemperatureUnit) -> (Option<f32>, &[u8; 8]) {
+    let glyph = match unit {
+        TemperatureUnit::Celsius => &font.celsius,
+        TemperatureUnit::Fahrenheit => &font.fahrenheit,
+        TemperatureUnit::Kelvin => &font.kelvin,
+    };
+    (celsius.map(|celsius| unit.convert(celsius)), glyph)
+}
+
+/// Draws the first variant fitting the display or the last one
+fn render(display: &mut display::LinearMatrixDisplay, variants: &[Vec<Glyph>]) -> Result<(), Error> {
+    let glyphs = fitting(variants, display.width());
+
+    // glyph starting at each column from the left edge, one column is left empty
+    let mut columns = vec![None; 1];
+    for (i, (_, width, gap)) in glyphs.iter().enumerate() {
+        columns.extend((0..*width).rev().map(|bit| Some((i, bit))));
+        columns.extend((0..*gap).map(|_| None));
+    }
+    display.draw(|x, y| match columns.get(x) {
+        Some(Some((i, bit))) => glyphs[*i].0[y] & (1 << bit),
+        _ => 0,
+    })
+}
+
+/// The first variant fitting the width, the last one when none does
+fn fitting<'a>(variants: &'a [Vec<Glyph<'a>>], width: usize) -> &'a [Glyph<'a>] {
+    let glyphs_width = |glyphs: &[Glyph]| glyphs.iter().map(|(_, width, gap)| width + gap).sum::<usize>();
+    variants
+        .iter()
+        // one empty column on the left instead of the gap after the last glyph
+        .find(|glyphs| glyphs_width(glyphs) <= width)
+        .or_else(|| variants.last())
+        .map_or(&[][..], |glyphs| glyphs.as_slice())
+}
+
+/// Digits of the value with the given number of decimals, a minus sign for negative values,
+/// dashes like `--.-` for the unknown value
+fn number(font: &Font, value: Option<f32>, decimals: usize) -> Vec<Glyph<'_>> {
+    let text = match value {
+        Some(value) => format!("{:.*}", decimals, value),
+        None if decimals > 0 => format!("--.{}", "-".repeat(decimals)),
+        None => "--".to_string(),
+    };
+    // "-0.0" is shown as "0.0"
+    let text = match text.strip_prefix('-') {
+        Some(rest) if rest.chars().all(|c| c == '0' || c == '.') && value.is_some() => rest.to_string(),
+        _ => text,
+    };
+    text.chars()
+        .filter_map(|c| match c {
+            '-' => Some((&font.minus, font.minus_width, 1)),
+            '.' => Some((&font.dot, font.dot_width, 1)),
+            _ => c.to_digit(10).map(|d| (&font.nums[d as usize], font.digit_width, 1)),
+        })
+        .collect()
 }
 
-fn measure(sensor: &mut AHT10<I2cdev>) -> Result<Weather, Error> {
-    let (t, h) = sensor.measure().context(error::SensorSnafu)?;
-    let weather = Weather { humidity: h as u8, temperature: t };
-    Ok(weather)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::Reading;
+    use chrono::TimeZone;
+
+    /// Four 8x8 matrices
+    const WIDTH: usize = 32;
+
+    /// Glyphs of the variant drawn on the display
+    fn shown(measurement: &Measurement, weather_type: WeatherType, slim: bool, unit: TemperatureUnit) -> Vec<[u8; 8]> {
+        let variants = variants(Some(measurement), weather_type, slim, unit);
+        fitting(&variants, WIDTH).iter().map(|(glyph, _, _)| **glyph).collect()
+    }
+
+    fn pressure(hpa: f32, unit: PressureUnit, slim: bool) -> Vec<[u8; 8]> {
+        let measurement = Measurement {
+            temperature: 20.0,
+            humidity: None,
+            pressure: Some(hpa),
+            co2: None,
+        };
+        shown(
+            &measurement,
+            WeatherType::Pressure(unit),
+            slim,
+            TemperatureUnit::Celsius,
+        )
+    }
+
+    fn temperature(celsius: f32, trend: Option<Trend>, unit: TemperatureUnit) -> Vec<[u8; 8]> {
+        let measurement = Measurement {
+            temperature: celsius,
+            humidity: None,
+            pressure: None,
+            co2: None,
+        };
+        shown(&measurement, WeatherType::Temperature(trend), false, unit)
+    }
+
+    fn humidity(humidity: f32, trend: Option<Trend>) -> Vec<[u8; 8]> {
+        let measurement = Measurement {
+            temperature: 20.0,
+            humidity: Some(humidity),
+            pressure: None,
+            co2: None,
+        };
+        shown(
+            &measurement,
+            WeatherType::Humidity(trend),
+            false,
+            TemperatureUnit::Celsius,
+        )
+    }
+
+    #[test]
+    fn shows_trend_over_threshold() {
+        let (window, start) = (Duration::from_secs(600), Instant::now());
+        let trend = |last: f32| {
+            let mut series = Series::new();
+            series.push(start, 20.0, window);
+            series.push(start + Duration::from_secs(240), 20.0, window);
+            // not enough time has passed
+            assert_eq!(series.trend(window, 0.5), None);
+            series.push(start + Duration::from_secs(300), last, window);
+            series.trend(window, 0.5)
+        };
+        assert_eq!(trend(20.5), Some(Trend::Rising));
+        assert_eq!(trend(20.4), Some(Trend::Steady));
+        assert_eq!(trend(19.6), Some(Trend::Steady));
+        assert_eq!(trend(19.5), Some(Trend::Falling));
+    }
+
+    #[test]
+    fn compares_with_oldest_value_in_window() {
+        let (window, start) = (Duration::from_secs(600), Instant::now());
+        let mut series = Series::new();
+        for (minute, value) in [(0, 18.0), (5, 20.0), (10, 20.0), (15, 20.2)] {
+            series.push(start + Duration::from_secs(minute * 60), value, window);
+        }
+        // 18.0 has left the window
+        assert_eq!(series.trend(window, 0.5), Some(Trend::Steady));
+        // the same reading seen again is skipped
+        series.push(start + Duration::from_secs(15 * 60), 25.0, window);
+        assert_eq!(series.values.back().map(|(_, value)| *value), Some(20.2));
+    }
+
+    #[test]
+    fn starts_ranges_over_at_midnight() {
+        let config = model::Config::new();
+        let mut observations = Observations::new(&config.weather);
+        let max_age = Duration::from_secs(60);
+        let readings = |temperature: f32| {
+            let reading = Reading {
+                measurement: Some(Measurement {
+                    temperature,
+                    humidity: Some(50.0),
+                    pressure: None,
+                    co2: None,
+                }),
+                time: Some(Instant::now()),
+                ..Reading::default()
+            };
+            Readings { sensor: reading, probes: vec![reading] }
+        };
+        let range = |range: Option<Range>| range.map(|range| (range.min, range.max));
+
+        let evening = Local.ymd(2022, 1, 15).and_hms(23, 59, 0);
+        observations.record(&readings(18.0), max_age, &evening);
+        observations.record(&readings(22.0), max_age, &(evening + chrono::Duration::seconds(30)));
+        assert_eq!(range(observations.temperature_range), Some((18.0, 22.0)));
+        assert_eq!(range(observations.probe_ranges[0]), Some((18.0, 22.0)));
+
+        observations.record(&readings(20.0), max_age, &Local.ymd(2022, 1, 16).and_hms(0, 0, 0));
+        assert_eq!(range(observations.temperature_range), Some((20.0, 20.0)));
+        assert_eq!(range(observations.humidity_range), Some((50.0, 50.0)));
+        assert_eq!(range(observations.probe_ranges[0]), Some((20.0, 20.0)));
+    }
+
+    #[test]
+    fn raises_co2_alerts_once() {
+        let config = model::Co2::default();
+        let mut watch = Co2Watch::new();
+        let steps = [
+            (800.0, None, Co2Level::Good),
+            (1000.0, Some(Co2Alert::Flash), Co2Level::Fair),
+            (1600.0, Some(Co2Alert::FlashAndBuzzer), Co2Level::Poor),
+            // kept until 50 ppm below the threshold
+            (1460.0, None, Co2Level::Poor),
+            (1440.0, None, Co2Level::Fair),
+            (960.0, None, Co2Level::Fair),
+            (940.0, None, Co2Level::Good),
+            (1000.0, Some(Co2Alert::Flash), Co2Level::Fair),
+        ];
+        for (co2, alert, level) in steps {
+            assert_eq!(watch.update(co2, &config), alert, "{} ppm", co2);
+            assert_eq!(watch.level, level, "{} ppm", co2);
+        }
+    }
+
+    #[test]
+    fn ignores_co2_wobbling_around_threshold() {
+        let config = model::Co2::default();
+        let mut watch = Co2Watch::new();
+        let alerts = [990.0, 1010.0, 960.0, 1030.0, 955.0, 1001.0, 980.0]
+            .iter()
+            .filter_map(|&co2| watch.update(co2, &config))
+            .collect::<Vec<_>>();
+        assert_eq!(alerts, [Co2Alert::Flash]);
+        assert_eq!(watch.level, Co2Level::Fair);
+    }
+
+    #[test]
+    fn shows_pressure_unit() {
+        let hpa = pressure(1013.2, PressureUnit::Hpa, false);
+        assert_eq!(hpa.len(), 5);
+        assert_eq!(
+            hpa[..4],
+            [
+                display::TINY_NUMS[1],
+                display::TINY_NUMS[0],
+                display::TINY_NUMS[1],
+                display::TINY_NUMS[3]
+            ]
+        );
+        assert_eq!(hpa[4], display::HECTOPASCAL);
+        assert_eq!(
+            pressure(999.6, PressureUnit::Hpa, true).last(),
+            Some(&display::SLIM_HECTOPASCAL)
+        );
+
+        // three digits of mmHg fit in the usual font
+        let mmhg = pressure(1013.2, PressureUnit::Mmhg, false);
+        assert_eq!(
+            mmhg,
+            [
+                display::NUMS[7],
+                display::NUMS[6],
+                display::NUMS[0],
+                display::MILLIMETRE
+            ]
+        );
+    }
+
+    #[test]
+    fn shows_frost_without_decimals() {
+        let (minus, nums) = (display::MINUS, display::NUMS);
+        assert_eq!(
+            temperature(-40.0, None, TemperatureUnit::Celsius),
+            [minus, nums[4], nums[0], display::CELSIUS]
+        );
+        // the arrow is kept, the decimals are dropped
+        assert_eq!(
+            temperature(-40.0, Some(Trend::Falling), TemperatureUnit::Celsius),
+            [minus, nums[4], nums[0], display::CELSIUS, display::TREND_FALLING]
+        );
+        // "-0.0" is not shown
+        assert_eq!(
+            temperature(-0.04, None, TemperatureUnit::Celsius),
+            [nums[0], display::DOT, nums[0], display::CELSIUS]
+        );
+    }
+
+    #[test]
+    fn shows_three_digits_of_fahrenheit() {
+        let nums = display::NUMS;
+        // 40°C is 104°F
+        assert_eq!(
+            temperature(40.0, None, TemperatureUnit::Fahrenheit),
+            [nums[1], nums[0], nums[4], display::FAHRENHEIT]
+        );
+        assert_eq!(
+            temperature(40.0, Some(Trend::Rising), TemperatureUnit::Fahrenheit),
+            [nums[1], nums[0], nums[4], display::FAHRENHEIT, display::TREND_RISING]
+        );
+    }
+
+    #[test]
+    fn drops_unit_when_nothing_else_fits() {
+        let (minus, nums) = (display::MINUS, display::NUMS);
+        // -73.4°C is -100°F, the narrowest variant is the number alone
+        assert_eq!(
+            temperature(-73.4, Some(Trend::Steady), TemperatureUnit::Fahrenheit),
+            [minus, nums[1], nums[0], nums[0]]
+        );
+    }
+
+    #[test]
+    fn shows_full_humidity() {
+        let nums = display::NUMS;
+        // the icon does not fit next to three digits
+        assert_eq!(humidity(100.0, None), [nums[1], nums[0], nums[0], display::PERCENT]);
+        assert_eq!(
+            humidity(100.4, Some(Trend::Steady)),
+            [nums[1], nums[0], nums[0], display::PERCENT, display::TREND_STEADY]
+        );
+        // over 100% after the calibration
+        assert_eq!(humidity(103.0, None), [nums[1], nums[0], nums[0], display::PERCENT]);
+        assert_eq!(
+            humidity(45.0, None),
+            [display::HUMIDITY, nums[4], nums[5], display::PERCENT]
+        );
+    }
 }